@@ -6,7 +6,12 @@ fn main() {
     let start = std::time::Instant::now();
 
     let config = nccl::parse_config(&content).unwrap();
-    let num = walk(&config);
+    let mut num = 0;
+    config.walk(|depth, _| {
+        if depth != 0 {
+            num += 1;
+        }
+    });
 
     let end = std::time::Instant::now();
     let elapsed = end - start;
@@ -23,22 +28,14 @@ fn main() {
     println!("finished {elapsed:?}");
 }
 
-fn walk(config: &nccl::Config) -> usize {
-    let mut acc = config.children().count();
-    for child in config.children() {
-        acc += walk(child);
-    }
-    acc
-}
-
-fn random<'a>(config: &nccl::Config<'a>) -> Vec<&'a str> {
+fn random<'a>(config: &'a nccl::Config) -> Vec<&'a str> {
     let mut vec = Vec::new();
     random_rec(config, &mut vec);
 
     vec
 }
 
-fn random_rec<'a>(config: &nccl::Config<'a>, acc: &mut Vec<&'a str>) {
+fn random_rec<'a>(config: &'a nccl::Config, acc: &mut Vec<&'a str>) {
     let children = config.children().collect::<Vec<_>>();
     let random = children.choose(&mut rand::thread_rng());
 
@@ -0,0 +1,98 @@
+// a benchmark harness for parsing examples/big.nccl. it only reports
+// numbers; pre-sizing child maps didn't measurably help, so the parser
+// doesn't do it
+//
+//     cargo run --release --example parse_big [runs]
+
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+// counts allocations, including reallocations, to measure parsing without
+// timing noise
+struct Counting;
+
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+static BYTES: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for Counting {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        BYTES.fetch_add(layout.size(), Ordering::Relaxed);
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        BYTES.fetch_add(new_size.saturating_sub(layout.size()), Ordering::Relaxed);
+        System.realloc(ptr, layout, new_size)
+    }
+}
+
+#[global_allocator]
+static GLOBAL: Counting = Counting;
+
+fn main() {
+    let content = std::fs::read_to_string("examples/big.nccl").unwrap();
+    let runs = std::env::args()
+        .nth(1)
+        .and_then(|runs| runs.parse().ok())
+        .unwrap_or(100);
+
+    measure("parse", runs, || (), |()| nccl::parse_config(&content));
+
+    // layering a small config on a large base shouldn't cost as much as
+    // parsing the base again
+    let base = nccl::parse_config(&content).unwrap();
+    let layer = "extra\n    value\n";
+    measure(
+        "layer",
+        runs,
+        || base.clone(),
+        |base| nccl::parse_config_with_owned_base(base, layer),
+    );
+}
+
+// prints the allocations of one call to `f`, and how long it takes over
+// several runs, not counting `setup`
+fn measure<S, T>(
+    name: &str,
+    runs: usize,
+    mut setup: impl FnMut() -> S,
+    mut f: impl FnMut(S) -> Result<T, nccl::NcclError>,
+) {
+    let input = setup();
+    let (allocations, bytes) = (
+        ALLOCATIONS.load(Ordering::Relaxed),
+        BYTES.load(Ordering::Relaxed),
+    );
+    let output = f(input).unwrap();
+    let allocations = ALLOCATIONS.load(Ordering::Relaxed) - allocations;
+    let bytes = BYTES.load(Ordering::Relaxed) - bytes;
+    drop(output);
+
+    let mut times = (0..runs)
+        .map(|_| {
+            let input = setup();
+            let start = std::time::Instant::now();
+            let output = f(input).unwrap();
+            let elapsed = start.elapsed();
+            drop(output);
+            elapsed
+        })
+        .collect::<Vec<_>>();
+    times.sort();
+
+    println!(
+        "{}: {} allocations, {} bytes, {} runs, min {:?}, median {:?}",
+        name,
+        allocations,
+        bytes,
+        runs,
+        times[0],
+        times[times.len() / 2]
+    );
+}
@@ -4,12 +4,44 @@ use crate::parser::TOP_LEVEL_KEY;
 use crate::scanner::{QuoteKind, Span};
 use crate::NcclError;
 
-use std::hash::{Hash, Hasher};
-use std::ops::Index;
+use alloc::borrow::Cow;
+use alloc::collections::VecDeque;
+use alloc::string::String;
+use alloc::vec::Vec;
+use alloc::{format, vec};
+use core::fmt;
+use core::hash::{Hash, Hasher};
+use core::ops::{Index, IndexMut};
+use core::str::FromStr;
 
 #[cfg(not(fuzzing))]
 use indexmap::IndexMap;
 
+/// How [`Config::merge_with_policy`] handles a key with values in both configs
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum MergePolicy {
+    /// Keep the values of both. This is what [`Config::merge`] does.
+    #[default]
+    Append,
+    /// Replace the existing values with the other config's.
+    ReplaceLeaf,
+    /// Keep the existing values, ignoring the other config's.
+    KeepExisting,
+}
+
+/// Which comment [`Config::merge_comments`] keeps for a node that has one in
+/// both configs
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum CommentMergePolicy {
+    /// Keep the existing comment, using the other config's only if there is
+    /// none. This is what [`Config::merge`] does.
+    #[default]
+    KeepExisting,
+    /// Use the other config's comment, keeping the existing one only if the
+    /// other has none. This is what [`Config::merge_preferring_other`] does.
+    PreferOther,
+}
+
 /// Type alias for an [`IndexMap`], a hash map where insertion order is preserved.
 #[cfg(not(fuzzing))]
 pub type HashMap<K, V> = IndexMap<K, V, fnv::FnvBuildHasher>;
@@ -73,11 +105,18 @@ pub(crate) fn make_map<K, V>() -> HashMap<K, V> {
 #[cfg_attr(fuzzing, derive(arbitrary::Arbitrary))]
 pub struct Config<'a> {
     pub(crate) quotes: Option<QuoteKind>,
-    pub(crate) key: &'a str,
-    pub(crate) value: HashMap<&'a str, Config<'a>>,
+    pub(crate) key: Cow<'a, str>,
+    pub(crate) value: HashMap<Cow<'a, str>, Config<'a>>,
     pub(crate) span: Span,
+    pub(crate) comment: Option<Cow<'a, str>>,
+    pub(crate) trailing_comment: Option<Cow<'a, str>>,
+    pub(crate) indent_unit: Option<&'a str>,
+    pub(crate) comment_char: char,
 }
 
+/// Two configs are equal if they have the same keys, quoting, and children,
+/// regardless of the order of the children. Use [`Config::eq_ordered`] to
+/// also compare order. Spans are not compared.
 impl PartialEq for Config<'_> {
     fn eq(&self, rhs: &Config<'_>) -> bool {
         self.quoted() == rhs.quoted() && self.key == rhs.key && self.value == rhs.value
@@ -90,27 +129,294 @@ impl Hash for Config<'_> {
     }
 }
 
-impl<'a> Config<'a> {
-    pub(crate) fn new(key: &'a str, quotes: Option<QuoteKind>) -> Self {
-        Config {
-            quotes,
-            key,
-            value: make_map(),
-            span: Span::default(),
+// dropping the children recursively could overflow the stack on a deeply
+// nested config, so take them apart with an explicit stack instead
+impl Drop for Config<'_> {
+    fn drop(&mut self) {
+        if self.value.is_empty() {
+            return;
+        }
+
+        let mut stack = core::mem::take(&mut self.value)
+            .into_values()
+            .collect::<Vec<_>>();
+        while let Some(mut node) = stack.pop() {
+            stack.extend(core::mem::take(&mut node.value).into_values());
         }
     }
+}
+
+impl<'a> Config<'a> {
+    /// Create a new node with no children.
+    ///
+    /// Use [`TOP_LEVEL_KEY`] as the key to create a node that serializes as a
+    /// whole file rather than as a single key.
+    ///
+    /// ```
+    /// # use nccl::{Config, parser::TOP_LEVEL_KEY};
+    /// let mut config = Config::new(TOP_LEVEL_KEY, None);
+    /// config.insert_child("key");
+    /// assert_eq!(config.to_string(), "key\n");
+    /// ```
+    pub fn new(key: &'a str, quotes: Option<QuoteKind>) -> Self {
+        Config::new_with_span(Cow::Borrowed(key), Span::default(), quotes)
+    }
 
-    pub(crate) fn new_with_span(key: &'a str, span: Span, quotes: Option<QuoteKind>) -> Self {
+    pub(crate) fn new_with_span(key: Cow<'a, str>, span: Span, quotes: Option<QuoteKind>) -> Self {
         Config {
             quotes,
             key,
             value: make_map(),
             span,
+            comment: None,
+            trailing_comment: None,
+            indent_unit: None,
+            comment_char: '#',
         }
     }
 
     pub(crate) fn add_child(&mut self, child: Config<'a>) {
-        self.value.insert(child.key, child);
+        self.value.insert(child.key.clone(), child);
+    }
+
+    /// Add an empty child node if it does not already exist.
+    pub fn insert_child(&mut self, key: &'a str) {
+        self.entry(key);
+    }
+
+    /// Get a child node, inserting an empty one if it does not exist.
+    ///
+    /// ```
+    /// let mut config = nccl::Config::new("top", None);
+    /// config.entry("server").entry("port").entry("80");
+    /// assert!(config.has_path(["server", "port", "80"]));
+    /// ```
+    pub fn entry(&mut self, key: &'a str) -> &mut Config<'a> {
+        self.value
+            .entry(Cow::Borrowed(key))
+            .or_insert_with(|| Config::new(key, None))
+    }
+
+    /// Merge another config into this one.
+    ///
+    /// Children of `other` which this config already has are merged
+    /// recursively, and the rest are added after the existing children, the
+    /// same as [`crate::parse_config_with`].
+    pub fn merge(&mut self, other: &Config<'a>) {
+        self.merge_with_policy(other, MergePolicy::Append);
+    }
+
+    /// Parse `content` on top of this config, in place.
+    ///
+    /// Equivalent to replacing `self` with the result of
+    /// [`crate::parse_config_with`]. On error, `self` is left unchanged.
+    ///
+    /// ```
+    /// # use nccl::*;
+    /// let mut config = parse_config("beans\n    four\n").unwrap();
+    /// config.merge_from_str("beans\n    none\n").unwrap();
+    /// assert_eq!(config["beans"].values().collect::<Vec<_>>(), vec!["four", "none"]);
+    /// ```
+    pub fn merge_from_str(&mut self, content: &'a str) -> Result<(), NcclError> {
+        *self = crate::parse_config_with(self, content)?;
+        Ok(())
+    }
+
+    /// Merge another config into this one, resolving keys whose values
+    /// conflict according to a [`MergePolicy`].
+    ///
+    /// A key whose children are all leaves is treated as having values, and
+    /// is subject to the policy. Any other key is merged recursively. Comments
+    /// are merged with [`CommentMergePolicy::KeepExisting`].
+    ///
+    /// ```
+    /// # use nccl::*;
+    /// let mut config = parse_config("port\n    80\n").unwrap();
+    /// let other = parse_config("port\n    8080\n").unwrap();
+    /// config.merge_with_policy(&other, MergePolicy::ReplaceLeaf);
+    /// assert_eq!(config["port"].values().collect::<Vec<_>>(), vec!["8080"]);
+    /// ```
+    pub fn merge_with_policy(&mut self, other: &Config<'a>, policy: MergePolicy) {
+        self.merge_comment(other, CommentMergePolicy::KeepExisting);
+        for child in other.children() {
+            match self.value.get_mut(child.key()) {
+                None => self.add_child(child.clone()),
+
+                Some(existing) if existing.has_grandchildren() || child.has_grandchildren() => {
+                    existing.merge_with_policy(child, policy)
+                }
+
+                Some(existing) => match policy {
+                    MergePolicy::Append => existing.merge_with_policy(child, policy),
+                    MergePolicy::ReplaceLeaf if !child.is_empty() => {
+                        existing.value = child.value.clone()
+                    }
+                    MergePolicy::KeepExisting if existing.is_empty() => {
+                        existing.value = child.value.clone()
+                    }
+                    MergePolicy::ReplaceLeaf | MergePolicy::KeepExisting => {}
+                },
+            }
+        }
+    }
+
+    /// Merge another config into this one, putting the children of `other`
+    /// first so that [`Config::value`] prefers them.
+    ///
+    /// Children which this config already has are merged recursively, and the
+    /// rest of this config's children are added after those of `other`.
+    /// Comments are merged with [`CommentMergePolicy::PreferOther`].
+    ///
+    /// ```
+    /// # use nccl::*;
+    /// let mut config = parse_config("port\n    80\n").unwrap();
+    /// let other = parse_config("port\n    8080\n").unwrap();
+    /// config.merge_preferring_other(&other);
+    /// assert_eq!(config["port"].value(), Some("8080"));
+    /// assert_eq!(config["port"].values().collect::<Vec<_>>(), vec!["8080", "80"]);
+    /// ```
+    pub fn merge_preferring_other(&mut self, other: &Config<'a>) {
+        self.merge_comment(other, CommentMergePolicy::PreferOther);
+
+        // an IndexMap can't insert at the front, so rebuild it in order
+        let mut rest = Config::new_with_span(self.key.clone(), self.span, self.quotes);
+        core::mem::swap(&mut rest.value, &mut self.value);
+
+        for child in other.children() {
+            match rest.remove(child.key()) {
+                Some(mut existing) => {
+                    existing.merge_preferring_other(child);
+                    self.add_child(existing);
+                }
+                None => self.add_child(child.clone()),
+            }
+        }
+
+        for child in core::mem::take(&mut rest.value).into_values() {
+            self.add_child(child);
+        }
+    }
+
+    /// Merge the comments of another config into this one, without changing
+    /// any keys.
+    ///
+    /// Each node which both configs have gets its comment chosen according to
+    /// the [`CommentMergePolicy`], as does the trailing comment of this node.
+    /// Nodes only `other` has are ignored.
+    ///
+    /// ```
+    /// # use nccl::*;
+    /// let options = ParseOptions {
+    ///     keep_comments: true,
+    ///     ..ParseOptions::default()
+    /// };
+    /// let mut config = parse_config_with_options("# mine\nport\n    80\n", options).unwrap();
+    /// let other = parse_config_with_options("# theirs\nport\n    80\n", options).unwrap();
+    /// config.merge_comments(&other, CommentMergePolicy::PreferOther);
+    /// assert_eq!(config["port"].comment(), Some("# theirs"));
+    /// ```
+    pub fn merge_comments(&mut self, other: &Config<'a>, policy: CommentMergePolicy) {
+        self.merge_comment(other, policy);
+        for child in other.children() {
+            if let Some(existing) = self.value.get_mut(child.key()) {
+                existing.merge_comments(child, policy);
+            }
+        }
+    }
+
+    fn merge_comment(&mut self, other: &Config<'a>, policy: CommentMergePolicy) {
+        let merge = |existing: &mut Option<Cow<'a, str>>, other: &Option<Cow<'a, str>>| {
+            *existing = match policy {
+                CommentMergePolicy::KeepExisting => existing.take().or_else(|| other.clone()),
+                CommentMergePolicy::PreferOther => other.clone().or_else(|| existing.take()),
+            };
+        };
+        merge(&mut self.comment, &other.comment);
+        merge(&mut self.trailing_comment, &other.trailing_comment);
+    }
+
+    fn has_grandchildren(&self) -> bool {
+        self.children().any(|child| !child.is_empty())
+    }
+
+    /// Remove a child node, returning it if it existed.
+    ///
+    /// The order of the remaining children is preserved.
+    pub fn remove(&mut self, key: &str) -> Option<Config<'a>> {
+        #[cfg(not(fuzzing))]
+        return self.value.shift_remove(key);
+
+        #[cfg(fuzzing)]
+        return self.value.remove(key);
+    }
+
+    /// Remove the children of a node for which `f` returns `false`.
+    ///
+    /// The order of the remaining children is preserved.
+    ///
+    /// ```
+    /// let source = "a\n    1\nb\n    2\nc\n    3\n";
+    /// let mut config = nccl::parse_config(&source).unwrap();
+    /// config.retain(|key, _| key != "b");
+    /// assert_eq!(config.values().collect::<Vec<_>>(), vec!["a", "c"]);
+    /// ```
+    pub fn retain<F: FnMut(&str, &Config<'a>) -> bool>(&mut self, mut f: F) {
+        self.value.retain(|key, child| f(key, child));
+    }
+
+    /// Change the key of a child node, keeping its children and its position
+    /// among its siblings.
+    ///
+    /// Returns `false` without changing anything if there is no child `old`,
+    /// or if there is already a child `new`.
+    ///
+    /// ```
+    /// let source = "a\n    1\nb\n    2\n";
+    /// let mut config = nccl::parse_config(&source).unwrap();
+    /// assert!(config.rename_key("a", "c"));
+    /// assert_eq!(config.values().collect::<Vec<_>>(), vec!["c", "b"]);
+    /// ```
+    pub fn rename_key(&mut self, old: &str, new: &'a str) -> bool {
+        if self.value.contains_key(new) {
+            return false;
+        }
+
+        #[cfg(not(fuzzing))]
+        {
+            let Some((index, _, mut node)) = self.value.shift_remove_full(old) else {
+                return false;
+            };
+            node.key = Cow::Borrowed(new);
+            let (last, _) = self.value.insert_full(node.key.clone(), node);
+            self.value.move_index(last, index);
+            true
+        }
+
+        #[cfg(fuzzing)]
+        {
+            let Some(mut node) = self.value.remove(old) else {
+                return false;
+            };
+            node.key = Cow::Borrowed(new);
+            self.value.insert(node.key.clone(), node);
+            true
+        }
+    }
+
+    /// Add an empty child node, returning `self` for chaining.
+    ///
+    /// Does nothing if the child already exists.
+    pub fn with_child(mut self, key: &'a str) -> Self {
+        self.entry(key);
+        self
+    }
+
+    /// Add a child node with a single value, returning `self` for chaining.
+    ///
+    /// If the child already exists, the value is added to it.
+    pub fn with_value(mut self, key: &'a str, value: &'a str) -> Self {
+        self.entry(key).entry(value);
+        self
     }
 
     pub fn quoted(&self) -> bool {
@@ -126,11 +432,176 @@ impl<'a> Config<'a> {
         self.value.contains_key(value)
     }
 
+    /// Check whether the full chain of keys exists below the node.
+    ///
+    /// ```
+    /// let source = "a\n    b\n        c\n";
+    /// let config = nccl::parse_config(&source).unwrap();
+    /// assert!(config.has_path(["a", "b", "c"]));
+    /// assert!(!config.has_path(["a", "c"]));
+    /// ```
+    pub fn has_path<'b, I: IntoIterator<Item = &'b str>>(&self, path: I) -> bool {
+        path.into_iter()
+            .try_fold(self, |node, key| node.get(key))
+            .is_some()
+    }
+
+    /// Get a child node, if it exists.
+    ///
+    /// ```
+    /// let source = "key\n    value\n";
+    /// let config = nccl::parse_config(&source).unwrap();
+    /// assert!(config.get("key").is_some());
+    /// assert!(config.get("value").is_none());
+    /// ```
+    pub fn get(&self, key: &str) -> Option<&Config<'a>> {
+        self.value.get(key)
+    }
+
+    /// Every child node whose value matches a key.
+    ///
+    /// Sibling keys with identical text are merged while parsing (see
+    /// [`crate::DuplicatePolicy`]), so unlike [`Config::get`] this also
+    /// matches children whose value is equal to `key` once escape sequences
+    /// are processed by [`Config::parse_quoted`].
+    ///
+    /// ```
+    /// let source = "key\n    \"a\\tb\"\n    'a\tb'\n    c\n";
+    /// let config = nccl::parse_config(&source).unwrap();
+    /// assert_eq!(config["key"].get_all("a\tb").count(), 2);
+    /// ```
+    pub fn get_all<'b>(&'b self, key: &'b str) -> impl Iterator<Item = &'b Config<'a>> + 'b {
+        self.children().filter(move |child| {
+            child.key == key
+                || (child.key.contains('\\')
+                    && matches!(child.parse_quoted(), Ok(value) if value == key))
+        })
+    }
+
+    /// The number of children of a node.
+    pub fn len(&self) -> usize {
+        self.value.len()
+    }
+
+    /// Check whether the node has no children.
+    pub fn is_empty(&self) -> bool {
+        self.value.is_empty()
+    }
+
+    /// Check whether the node is a leaf, a value with no children.
+    ///
+    /// ```
+    /// let source = "key\n    value\n";
+    /// let config = nccl::parse_config(&source).unwrap();
+    /// assert!(config["key"]["value"].is_leaf());
+    /// assert!(!config["key"].is_leaf());
+    /// ```
+    pub fn is_leaf(&self) -> bool {
+        self.value.is_empty()
+    }
+
     /// Iterator for the children of a node.
-    pub fn children(&self) -> impl Iterator<Item = &Config<'a>> {
+    pub fn children(&self) -> impl ExactSizeIterator<Item = &Config<'a>> {
         self.value.values()
     }
 
+    /// Iterator for the children of a node whose keys match a predicate.
+    ///
+    /// ```
+    /// let source = "logs\n    2023-12-31\n    2024-01-01\n    2024-01-02\n";
+    /// let config = nccl::parse_config(&source).unwrap();
+    /// assert_eq!(
+    ///     config["logs"].children_named(|key| key.starts_with("2024-")).count(),
+    ///     2
+    /// );
+    /// ```
+    pub fn children_named<F: Fn(&str) -> bool>(
+        &self,
+        pred: F,
+    ) -> impl Iterator<Item = &Config<'a>> {
+        self.children().filter(move |child| pred(child.key()))
+    }
+
+    /// Iterator for the children of a node paired with their keys.
+    pub fn entries(&self) -> impl Iterator<Item = (&str, &Config<'a>)> {
+        self.value.iter().map(|(key, child)| (&**key, child))
+    }
+
+    /// The map from keys to children underlying a node.
+    ///
+    /// ```
+    /// let source = "key\n a\n b\n";
+    /// let config = nccl::parse_config(&source).unwrap();
+    /// let (key, child) = config["key"].as_map().get_index(0).unwrap();
+    /// assert_eq!(*key, "a");
+    /// assert!(child.is_leaf());
+    /// ```
+    pub fn as_map(&self) -> &HashMap<Cow<'a, str>, Config<'a>> {
+        &self.value
+    }
+
+    /// The children of a node, collected in order for positional access.
+    ///
+    /// Allocates a new `Vec` on every call, so prefer [`Config::children`]
+    /// when iterating is enough.
+    ///
+    /// ```
+    /// let source = "key\n a\n b\n c\n";
+    /// let config = nccl::parse_config(&source).unwrap();
+    /// assert_eq!(config["key"].children_vec()[1].key(), "b");
+    /// ```
+    pub fn children_vec(&self) -> Vec<&Config<'a>> {
+        self.children().collect()
+    }
+
+    /// The children of a node, sorted by key.
+    ///
+    /// The order of the node itself is unchanged.
+    ///
+    /// ```
+    /// let source = "key\n c\n a\n b\n";
+    /// let config = nccl::parse_config(&source).unwrap();
+    /// assert_eq!(
+    ///     config["key"]
+    ///         .sorted_children()
+    ///         .iter()
+    ///         .map(|child| child.key())
+    ///         .collect::<Vec<_>>(),
+    ///     vec!["a", "b", "c"]
+    /// );
+    /// ```
+    pub fn sorted_children(&self) -> Vec<&Config<'a>> {
+        let mut children = self.children_vec();
+        children.sort_by_key(|child| child.key());
+        children
+    }
+
+    /// Follow a path of keys, ignoring case.
+    ///
+    /// Each segment is compared against every child of the current node, so
+    /// lookup is O(children) per segment rather than a hash lookup. The first
+    /// matching child in source order wins.
+    ///
+    /// ```
+    /// let source = "Server\n    Port\n        80\n";
+    /// let config = nccl::parse_config(&source).unwrap();
+    /// assert_eq!(
+    ///     config.get_path_ignore_case(&["server", "PORT"]).unwrap().value(),
+    ///     Some("80")
+    /// );
+    /// ```
+    pub fn get_path_ignore_case(&self, keys: &[&str]) -> Option<&Config<'a>> {
+        keys.iter().try_fold(self, |node, key| {
+            node.children().find(|child| {
+                child
+                    .key
+                    .chars()
+                    .flat_map(char::to_lowercase)
+                    .eq(key.chars().flat_map(char::to_lowercase))
+            })
+        })
+    }
+
     /// The first child of the node.
     ///
     /// ```
@@ -161,8 +632,8 @@ impl<'a> Config<'a> {
     /// let config = nccl::parse_config(&source).unwrap();
     /// assert_eq!(config["key"].key(), "key");
     /// ```
-    pub fn key(&self) -> &'a str {
-        self.key
+    pub fn key(&self) -> &str {
+        &self.key
     }
 
     /// The location in the source of this node.
@@ -177,281 +648,2787 @@ impl<'a> Config<'a> {
         self.span
     }
 
-    /// Iterator for the child values of a node.
-    pub fn values(&self) -> impl Iterator<Item = &str> {
-        self.value.keys().copied()
+    /// The comment lines directly above this node, if they were kept with
+    /// [`crate::ParseOptions::keep_comments`].
+    ///
+    /// ```
+    /// # use nccl::*;
+    /// let source = "# the port\nport\n    80\n";
+    /// let options = ParseOptions {
+    ///     keep_comments: true,
+    ///     ..ParseOptions::default()
+    /// };
+    /// let config = parse_config_with_options(source, options).unwrap();
+    /// assert_eq!(config["port"].comment(), Some("# the port"));
+    /// assert_eq!(config.to_string(), source);
+    /// ```
+    ///
+    /// If a key is repeated, the comments above each occurrence are joined.
+    pub fn comment(&self) -> Option<&str> {
+        self.comment.as_deref()
     }
 
-    /// The first child value of a node.
-    pub fn value(&self) -> Option<&'a str> {
-        self.value.iter().next().map(|opt| *opt.0)
+    /// The comment lines after the last key in the source, if they were kept
+    /// with [`crate::ParseOptions::keep_comments`]. Only the top-level node
+    /// has one.
+    ///
+    /// ```
+    /// # use nccl::*;
+    /// let source = "port\n    80\n# the end\n";
+    /// let options = ParseOptions {
+    ///     keep_comments: true,
+    ///     ..ParseOptions::default()
+    /// };
+    /// let config = parse_config_with_options(source, options).unwrap();
+    /// assert_eq!(config.trailing_comment(), Some("# the end"));
+    /// assert_eq!(config.to_string(), source);
+    /// ```
+    pub fn trailing_comment(&self) -> Option<&str> {
+        self.trailing_comment.as_deref()
     }
 
-    fn pretty_print(&self) -> String {
-        self.pp(0)
+    /// Iterator for the child values of a node.
+    pub fn values(&self) -> impl ExactSizeIterator<Item = &str> {
+        self.value.keys().map(|key| &**key)
     }
 
-    fn pp(&self, indent: usize) -> String {
-        let mut s = String::new();
-        if self.key != TOP_LEVEL_KEY && indent != 0 {
-            for _ in 0..indent - 1 {
-                s.push_str("    ");
-            }
-            if let Some(quote) = self.quotes {
-                s.push(quote.char());
-            }
-            s.push_str(self.key);
-            if let Some(quote) = self.quotes {
-                s.push(quote.char());
-            }
-            s.push('\n');
-        }
-        for (_, v) in self.value.iter() {
-            s.push_str(&v.pp(indent + 1));
-        }
-        s
+    /// Owned copies of the child values of a node.
+    ///
+    /// Quoted values are copied as written. See [`Config::values_parsed`].
+    pub fn values_owned(&self) -> Vec<String> {
+        self.values().map(String::from).collect()
     }
 
-    /// Parse the string including escape sequences if it's quoted.
+    /// The child values of a node, with escapes in quoted values processed
+    /// by [`Config::parse_quoted`].
     ///
-    /// Operates on the first child of the node. See [`Config::child`].
-    pub fn parse_quoted(&self) -> Result<String, NcclError> {
-        // TODO use a library for this garbage
-        if !self.quoted() {
-            Ok(String::from(self.key))
-        } else {
-            let mut value = Vec::with_capacity(self.key.len());
-
-            let bytes = self.key.as_bytes();
-            let mut i = 0;
+    /// ```
+    /// let source = "greeting\n    hello\n    \"hi\\tthere\"\n";
+    /// let config = nccl::parse_config(&source).unwrap();
+    /// assert_eq!(
+    ///     config["greeting"].values_parsed().unwrap(),
+    ///     vec!["hello", "hi\tthere"]
+    /// );
+    /// ```
+    pub fn values_parsed(&self) -> Result<Vec<String>, NcclError> {
+        self.children().map(Config::parse_quoted).collect()
+    }
 
-            while i < bytes.len() {
-                if bytes[i] == b'\\' {
-                    i += 1;
-                    if i >= bytes.len() {
-                        return Err(NcclError::UnterminatedString {
-                            start: self.span.line,
-                        });
-                    }
+    /// The first child value of a node.
+    pub fn value(&self) -> Option<&str> {
+        self.value.keys().next().map(|key| &**key)
+    }
 
-                    match bytes[i] {
-                        // \n
-                        b'n' => {
-                            value.push(b'\n');
-                            i += 1;
-                        }
+    /// The child value of a node at `index`, in the order they were inserted.
+    ///
+    /// ```
+    /// let source = "port\n    80\n    443\n";
+    /// let config = nccl::parse_config(&source).unwrap();
+    /// assert_eq!(config["port"].value_at(0), config["port"].value());
+    /// assert_eq!(config["port"].value_at(2), None);
+    /// ```
+    pub fn value_at(&self, index: usize) -> Option<&str> {
+        #[cfg(not(fuzzing))]
+        return self.value.get_index(index).map(|(key, _)| &**key);
 
-                        // \r
-                        b'r' => {
-                            value.push(b'\r');
-                            i += 1;
-                        }
+        #[cfg(fuzzing)]
+        return self.value.keys().nth(index).map(|key| &**key);
+    }
 
-                        // \\
-                        b'\\' => {
-                            value.push(b'\\');
-                            i += 1;
-                        }
+    /// The key of the first child of a node. The same as [`Config::value`].
+    pub fn first_key(&self) -> Option<&str> {
+        self.value()
+    }
 
-                        // \" or \'
-                        code @ (b'"' | b'\'') => {
-                            value.push(code);
-                            i += 1;
-                        }
+    /// The key of the last child of a node.
+    ///
+    /// ```
+    /// let source = "port\n    80\n    443\n";
+    /// let config = nccl::parse_config(&source).unwrap();
+    /// assert_eq!(config["port"].last_key(), Some("443"));
+    /// assert_eq!(config["port"]["443"].last_key(), None);
+    /// ```
+    pub fn last_key(&self) -> Option<&str> {
+        #[cfg(not(fuzzing))]
+        return self.value.last().map(|(key, _)| &**key);
 
-                        // something \
-                        //       more stuff
-                        b'\r' | b'\n' => {
-                            i += 1;
+        #[cfg(fuzzing)]
+        return self.value.keys().last().map(|key| &**key);
+    }
+
+    /// The first value of a child node.
+    ///
+    /// ```
+    /// let source = "server\n    root\n        /var/www/html\n";
+    /// let config = nccl::parse_config(&source).unwrap();
+    /// assert_eq!(config["server"].child_value("root"), Some("/var/www/html"));
+    /// assert_eq!(config["server"].child_value("port"), None);
+    /// ```
+    pub fn child_value(&self, key: &str) -> Option<&str> {
+        self.get(key)?.value()
+    }
+
+    /// The first child value of a node, or `default` if it has no children.
+    pub fn value_or<'s>(&'s self, default: &'s str) -> &'s str {
+        self.value().unwrap_or(default)
+    }
+
+    /// The first value of a child node, or `default` if there is no such
+    /// child or it has no children.
+    ///
+    /// ```
+    /// let source = "server\n    root\n        /var/www/html\n";
+    /// let config = nccl::parse_config(&source).unwrap();
+    /// assert_eq!(config["server"].child_value_or("port", "80"), "80");
+    /// ```
+    pub fn child_value_or<'s>(&'s self, key: &str, default: &'s str) -> &'s str {
+        self.child_value(key).unwrap_or(default)
+    }
+
+    /// The first child value of a node, parsed with [`str::parse`].
+    ///
+    /// ```
+    /// let source = "port\n    80\n";
+    /// let config = nccl::parse_config(&source).unwrap();
+    /// assert_eq!(config["port"].value_as::<u16>(), Some(Ok(80)));
+    /// ```
+    pub fn value_as<T: FromStr>(&self) -> Option<Result<T, T::Err>> {
+        self.value().map(str::parse)
+    }
+
+    /// The first child value of a node as a boolean.
+    ///
+    /// `true`, `t`, and `yes` are true, and `false`, `f`, and `no` are false,
+    /// ignoring ASCII case. Anything else is `None`.
+    ///
+    /// ```
+    /// let source = "debug\n    yes\n";
+    /// let config = nccl::parse_config(&source).unwrap();
+    /// assert_eq!(config["debug"].as_bool(), Some(true));
+    /// ```
+    pub fn as_bool(&self) -> Option<bool> {
+        let value = self.value()?;
+        if ["true", "t", "yes"]
+            .iter()
+            .any(|b| value.eq_ignore_ascii_case(b))
+        {
+            Some(true)
+        } else if ["false", "f", "no"]
+            .iter()
+            .any(|b| value.eq_ignore_ascii_case(b))
+        {
+            Some(false)
+        } else {
+            None
+        }
+    }
+
+    /// The first child value of a node as an integer, or `None` if it is not
+    /// one.
+    ///
+    /// ```
+    /// let source = "port\n    80\n";
+    /// let config = nccl::parse_config(&source).unwrap();
+    /// assert_eq!(config["port"].as_i64(), Some(80));
+    /// ```
+    pub fn as_i64(&self) -> Option<i64> {
+        self.value_as().and_then(Result::ok)
+    }
+
+    /// The first child value of a node as a float, or `None` if it is not one.
+    ///
+    /// ```
+    /// let source = "ratio\n    0.5\n";
+    /// let config = nccl::parse_config(&source).unwrap();
+    /// assert_eq!(config["ratio"].as_f64(), Some(0.5));
+    /// ```
+    pub fn as_f64(&self) -> Option<f64> {
+        self.value_as().and_then(Result::ok)
+    }
+
+    /// All child values of a node, parsed with [`str::parse`].
+    ///
+    /// Returns the first error encountered, or an empty `Vec` if the node has
+    /// no children.
+    ///
+    /// ```
+    /// let source = "ports\n    80\n    443\n";
+    /// let config = nccl::parse_config(&source).unwrap();
+    /// assert_eq!(config["ports"].values_as::<u16>(), Ok(vec![80, 443]));
+    /// ```
+    pub fn values_as<T: FromStr>(&self) -> Result<Vec<T>, T::Err> {
+        self.values().map(str::parse).collect()
+    }
+
+    /// Call a function on this node and every node below it, in pre-order.
+    ///
+    /// The function also receives the depth of the node, where this node is
+    /// depth 0.
+    ///
+    /// ```
+    /// let source = "a\n    b\n    c\nd\n";
+    /// let config = nccl::parse_config(&source).unwrap();
+    /// let mut keys = Vec::new();
+    /// config["a"].walk(|depth, node| keys.push((depth, node.key())));
+    /// assert_eq!(keys, vec![(0, "a"), (1, "b"), (1, "c")]);
+    /// ```
+    pub fn walk<'b, F: FnMut(usize, &'b Config<'a>)>(&'b self, mut f: F) {
+        // an explicit stack so that deep configs can't overflow the call stack
+        let mut stack = vec![(0, self)];
+        while let Some((depth, node)) = stack.pop() {
+            f(depth, node);
+            let start = stack.len();
+            stack.extend(node.children().map(|child| (depth + 1, child)));
+            stack[start..].reverse();
+        }
+    }
+
+    /// Every leaf value below this node, paired with the path of keys leading
+    /// to it joined by `.`.
+    ///
+    /// Leaves with the same parent each get their own entry.
+    ///
+    /// ```
+    /// let source = "server\n    port\n        80\n        443\n";
+    /// let config = nccl::parse_config(&source).unwrap();
+    /// assert_eq!(
+    ///     config.flatten(),
+    ///     vec![
+    ///         (String::from("server.port"), "80"),
+    ///         (String::from("server.port"), "443"),
+    ///     ]
+    /// );
+    /// ```
+    pub fn flatten(&self) -> Vec<(String, &str)> {
+        self.flatten_with(".")
+    }
+
+    /// Like [`Config::flatten`], but joining keys with `separator`.
+    pub fn flatten_with(&self, separator: &str) -> Vec<(String, &str)> {
+        let mut leaves = Vec::new();
+        self.flatten_into(&mut Vec::new(), separator, &mut leaves);
+        leaves
+    }
+
+    /// Lazily iterate over every leaf value below this node, paired with the
+    /// path of keys leading to it. The same order as [`Config::flatten`].
+    ///
+    /// ```
+    /// let source = "server\n    port\n        80\n        443\n";
+    /// let config = nccl::parse_config(&source).unwrap();
+    /// let mut leaves = config.iter_leaves();
+    /// assert_eq!(leaves.next(), Some((vec!["server", "port"], "80")));
+    /// assert_eq!(leaves.next(), Some((vec!["server", "port"], "443")));
+    /// assert_eq!(leaves.next(), None);
+    /// ```
+    pub fn iter_leaves(&self) -> impl Iterator<Item = (Vec<&str>, &str)> + '_ {
+        let mut stack = vec![(Vec::new(), self.children())];
+
+        core::iter::from_fn(move || loop {
+            let (path, children) = stack.last_mut()?;
+            match children.next() {
+                None => {
+                    stack.pop();
+                }
+                Some(child) if child.is_empty() => return Some((path.clone(), child.key())),
+                Some(child) => {
+                    let mut path = path.clone();
+                    path.push(child.key());
+                    stack.push((path, child.children()));
+                }
+            }
+        })
+    }
+
+    fn flatten_into<'b>(
+        &'b self,
+        path: &mut Vec<&'b str>,
+        separator: &str,
+        leaves: &mut Vec<(String, &'b str)>,
+    ) {
+        for child in self.children() {
+            if child.is_empty() {
+                leaves.push((path.join(separator), child.key()));
+            } else {
+                path.push(child.key());
+                child.flatten_into(path, separator, leaves);
+                path.pop();
+            }
+        }
+    }
+
+    /// Every node below this one with the given key, in pre-order.
+    ///
+    /// ```
+    /// let source = "a\n    enabled\n        yes\nb\n    c\n        enabled\n            no\n";
+    /// let config = nccl::parse_config(&source).unwrap();
+    /// assert_eq!(
+    ///     config
+    ///         .find_all("enabled")
+    ///         .iter()
+    ///         .map(|node| node.value())
+    ///         .collect::<Vec<_>>(),
+    ///     vec![Some("yes"), Some("no")]
+    /// );
+    /// ```
+    pub fn find_all<'b>(&'b self, key: &str) -> Vec<&'b Config<'a>> {
+        let mut found = Vec::new();
+        self.find_all_into(key, &mut found);
+        found
+    }
+
+    fn find_all_into<'b>(&'b self, key: &str, found: &mut Vec<&'b Config<'a>>) {
+        for child in self.children() {
+            if child.key == key {
+                found.push(child);
+            }
+            child.find_all_into(key, found);
+        }
+    }
+
+    /// The keys leading from this node to the first node below it, in
+    /// pre-order, which is `target` or equal to it.
+    ///
+    /// ```
+    /// let source = "server\n    port\n        80\n";
+    /// let config = nccl::parse_config(&source).unwrap();
+    /// let port = &config["server"]["port"];
+    /// assert_eq!(config.path_to(port), Some(vec!["server", "port"]));
+    /// assert_eq!(config.path_to(&config), None);
+    /// ```
+    pub fn path_to(&self, target: &Config) -> Option<Vec<&str>> {
+        let mut path = Vec::new();
+        self.path_to_into(target, &mut path).then_some(path)
+    }
+
+    fn path_to_into<'b>(&'b self, target: &Config, path: &mut Vec<&'b str>) -> bool {
+        for child in self.children() {
+            path.push(child.key());
+            if core::ptr::eq(child, target) || child == target || child.path_to_into(target, path) {
+                return true;
+            }
+            path.pop();
+        }
+        false
+    }
+
+    /// Every node below this one matching a pattern of keys separated by
+    /// `.`, where `*` matches any one key and `**` matches any number of keys,
+    /// including none.
+    ///
+    /// Each matching node is returned once.
+    ///
+    /// ```
+    /// let source = "a\n    enabled\n        yes\nb\n    c\n        enabled\n            no\n";
+    /// let config = nccl::parse_config(&source).unwrap();
+    /// assert_eq!(config.glob("*.enabled").len(), 1);
+    /// assert_eq!(config.glob("**.enabled").len(), 2);
+    /// ```
+    pub fn glob<'b>(&'b self, pattern: &str) -> Vec<&'b Config<'a>> {
+        let segments = pattern.split('.').collect::<Vec<_>>();
+        let mut found = Vec::new();
+        self.glob_into(&segments, &mut make_map(), &mut found);
+        found
+    }
+
+    // `visited` holds each node along with how many segments were left to
+    // match below it, since `**` can reach the same node with the same rest
+    // of the pattern in more than one way
+    fn glob_into<'b>(
+        &'b self,
+        segments: &[&str],
+        visited: &mut HashMap<(*const Config<'a>, usize), ()>,
+        found: &mut Vec<&'b Config<'a>>,
+    ) {
+        if visited.insert((self, segments.len()), ()).is_some() {
+            return;
+        }
+
+        match segments.split_first() {
+            None => found.push(self),
+
+            Some((&"**", rest)) => {
+                self.glob_into(rest, visited, found);
+                for child in self.children() {
+                    child.glob_into(segments, visited, found);
+                }
+            }
+
+            Some((&"*", rest)) => {
+                for child in self.children() {
+                    child.glob_into(rest, visited, found);
+                }
+            }
+
+            Some((key, rest)) => {
+                if let Some(child) = self.get(key) {
+                    child.glob_into(rest, visited, found);
+                }
+            }
+        }
+    }
+
+    /// Compare two configs, including the order of their children.
+    ///
+    /// ```
+    /// let a = nccl::parse_config("x\ny\n").unwrap();
+    /// let b = nccl::parse_config("y\nx\n").unwrap();
+    /// assert_eq!(a, b);
+    /// assert!(!a.eq_ordered(&b));
+    /// ```
+    pub fn eq_ordered(&self, other: &Config<'_>) -> bool {
+        self.quoted() == other.quoted()
+            && self.key == other.key
+            && self.len() == other.len()
+            && self
+                .children()
+                .zip(other.children())
+                .all(|(a, b)| a.eq_ordered(b))
+    }
+
+    /// Compare two configs by their unescaped keys, ignoring whether and how
+    /// they were quoted. Like `==`, the order of children is not compared.
+    ///
+    /// ```
+    /// let a = nccl::parse_config("key\n    'say \"hi\"'\n").unwrap();
+    /// let b = nccl::parse_config("key\n    \"say \\\"hi\\\"\"\n").unwrap();
+    /// assert_ne!(a, b);
+    /// assert!(a.subtree_eq(&b));
+    /// ```
+    pub fn subtree_eq(&self, other: &Config) -> bool {
+        if self.unescaped_key() != other.unescaped_key() {
+            return false;
+        }
+
+        let mut pending = vec![(self, other)];
+        while let Some((ours, theirs)) = pending.pop() {
+            if ours.len() != theirs.len() {
+                return false;
+            }
+
+            // pair up children by their sorted unescaped keys, so each child
+            // is matched with exactly one other
+            for ((our_key, our_child), (their_key, their_child)) in ours
+                .unescaped_children()
+                .into_iter()
+                .zip(theirs.unescaped_children())
+            {
+                if our_key != their_key {
+                    return false;
+                }
+                pending.push((our_child, their_child));
+            }
+        }
+
+        true
+    }
+
+    fn unescaped_children(&self) -> Vec<(Cow<'_, str>, &Config<'a>)> {
+        let mut children = self
+            .children()
+            .map(|child| (child.unescaped_key(), child))
+            .collect::<Vec<_>>();
+        children.sort_by(|(a, _), (b, _)| a.cmp(b));
+        children
+    }
+
+    // an invalid escape can't be parsed, so fall back on the key as written
+    fn unescaped_key(&self) -> Cow<'_, str> {
+        match self.parse_quoted() {
+            Ok(key) if self.quoted() => Cow::Owned(key),
+            _ => Cow::Borrowed(self.key()),
+        }
+    }
+
+    /// The number of nodes below this one with no children. A node with no
+    /// children has no leaves below it.
+    ///
+    /// ```
+    /// let source = "a\n    b\n        c\n    d\n";
+    /// let config = nccl::parse_config(&source).unwrap();
+    /// assert_eq!(config.count_leaves(), 2);
+    /// assert_eq!(config["a"]["d"].count_leaves(), 0);
+    /// ```
+    pub fn count_leaves(&self) -> usize {
+        self.children()
+            .map(|child| match child.count_leaves() {
+                0 => 1,
+                leaves => leaves,
+            })
+            .sum()
+    }
+
+    /// The length of the longest path from this node to a leaf.
+    ///
+    /// ```
+    /// let source = "a\n    b\n        c\n    d\n";
+    /// let config = nccl::parse_config(&source).unwrap();
+    /// assert_eq!(config["a"].depth(), 2);
+    /// assert_eq!(config["a"]["d"].depth(), 0);
+    /// ```
+    pub fn depth(&self) -> usize {
+        self.children()
+            .map(|child| child.depth() + 1)
+            .max()
+            .unwrap_or(0)
+    }
+
+    /// The number of nodes at each depth below this one.
+    ///
+    /// Index 0 is this node, index 1 its children, and so on.
+    ///
+    /// ```
+    /// let source = "a\n    b\n    c\nd\n";
+    /// let config = nccl::parse_config(&source).unwrap();
+    /// assert_eq!(config.depth_histogram(), vec![1, 2, 2]);
+    /// ```
+    pub fn depth_histogram(&self) -> Vec<usize> {
+        let mut histogram = Vec::new();
+        let mut queue = VecDeque::from([(0, self)]);
+
+        while let Some((depth, node)) = queue.pop_front() {
+            if histogram.len() <= depth {
+                histogram.push(0);
+            }
+            histogram[depth] += 1;
+            queue.extend(node.children().map(|child| (depth + 1, child)));
+        }
+
+        histogram
+    }
+
+    /// Serialize the config as JSON.
+    ///
+    /// A node whose children are all leaves becomes a string if it has one
+    /// child, or an array of strings if it has more. Any other node becomes an
+    /// object, where leaves are strings of their own key. The top level is
+    /// always an object, even if all of its children are leaves. Quoted keys
+    /// have their escape sequences processed by [`Config::parse_quoted`], or
+    /// are emitted as written if they have an invalid escape.
+    ///
+    /// ```
+    /// let source = "server\n    port\n        80\n        443\n    root\n        /var/www\n";
+    /// let config = nccl::parse_config(&source).unwrap();
+    /// assert_eq!(
+    ///     config.to_json(),
+    ///     r#"{"server":{"port":["80","443"],"root":"/var/www"}}"#
+    /// );
+    /// ```
+    pub fn to_json(&self) -> String {
+        let mut json = String::new();
+        if self.key == TOP_LEVEL_KEY {
+            self.json_object_into(&mut json);
+        } else {
+            self.json_into(&mut json);
+        }
+        json
+    }
+
+    fn json_into(&self, json: &mut String) {
+        if self.is_empty() {
+            json_string(&self.unescaped_key(), json);
+        } else if self.children().all(Config::is_empty) {
+            if self.len() == 1 {
+                self.child().unwrap().json_into(json);
+            } else {
+                json.push('[');
+                for (i, child) in self.children().enumerate() {
+                    if i != 0 {
+                        json.push(',');
+                    }
+                    child.json_into(json);
+                }
+                json.push(']');
+            }
+        } else {
+            self.json_object_into(json);
+        }
+    }
+
+    fn json_object_into(&self, json: &mut String) {
+        json.push('{');
+        for (i, child) in self.children().enumerate() {
+            if i != 0 {
+                json.push(',');
+            }
+            json_string(&child.unescaped_key(), json);
+            json.push(':');
+            child.json_into(json);
+        }
+        json.push('}');
+    }
+
+    /// Serialize the config back into nccl source.
+    ///
+    /// Each top-level key is indented the same way it was in the source, or
+    /// with four spaces if it was not parsed. Quoted keys keep their quotes.
+    /// Parsing the output results in an equal config.
+    ///
+    /// ```
+    /// let source = "a\n\tb\n\t'c'\nd\n  e\n";
+    /// let config = nccl::parse_config(&source).unwrap();
+    /// assert_eq!(config.pretty_print(), source);
+    /// assert_eq!(nccl::parse_config(&config.pretty_print()).unwrap(), config);
+    /// ```
+    pub fn pretty_print(&self) -> String {
+        let mut s = String::new();
+        self.pp(&mut s, 0, None, true)
+            .expect("writing to a string can't fail");
+        s
+    }
+
+    /// Serialize the config, indenting each level with `indent`.
+    ///
+    /// ```
+    /// let source = "a\n    b\n        c\n";
+    /// let config = nccl::parse_config(&source).unwrap();
+    /// assert_eq!(config.pretty_print_with("\t").unwrap(), "a\n\tb\n\t\tc\n");
+    /// ```
+    ///
+    /// Returns [`NcclError::InvalidPrintIndent`] if `indent` is empty, or is
+    /// not made up entirely of spaces or entirely of tabs, since the output
+    /// would not parse back to the same config.
+    pub fn pretty_print_with(&self, indent: &str) -> Result<String, NcclError> {
+        if indent.is_empty()
+            || !(indent.bytes().all(|b| b == b' ') || indent.bytes().all(|b| b == b'\t'))
+        {
+            return Err(NcclError::InvalidPrintIndent {
+                indent: String::from(indent),
+            });
+        }
+
+        let mut s = String::new();
+        self.pp(&mut s, 0, Some(indent), true)
+            .expect("writing to a string can't fail");
+        Ok(s)
+    }
+
+    /// Serialize the config using the least indentation possible.
+    ///
+    /// Each level is indented by a single space, and nothing but the keys
+    /// themselves is emitted.
+    ///
+    /// ```
+    /// let source = "a\n    b\n\n# comment\nc\n    d\n        e\n";
+    /// let config = nccl::parse_config(&source).unwrap();
+    /// assert_eq!(config.to_string_minified(), "a\n b\nc\n d\n  e\n");
+    /// ```
+    pub fn to_string_minified(&self) -> String {
+        let mut s = String::new();
+        self.pp(&mut s, 0, Some(" "), false)
+            .expect("writing to a string can't fail");
+        s
+    }
+
+    // with no unit, each top-level key uses the indentation it was parsed with
+    fn pp<W: fmt::Write>(
+        &self,
+        w: &mut W,
+        indent: usize,
+        unit: Option<&str>,
+        comments: bool,
+    ) -> fmt::Result {
+        // an explicit stack so that deep configs can't overflow the call stack
+        let mut stack = vec![(self, indent, unit)];
+        while let Some((node, indent, unit)) = stack.pop() {
+            let unit = match unit {
+                None if node.key == TOP_LEVEL_KEY => None,
+                None => Some(node.indent_unit.unwrap_or("    ")),
+                unit => unit,
+            };
+
+            if node.key != TOP_LEVEL_KEY && indent != 0 {
+                node.pp_key(w, indent, unit.unwrap_or("    "), comments)?;
+            }
+
+            let start = stack.len();
+            stack.extend(node.children().map(|child| (child, indent + 1, unit)));
+            stack[start..].reverse();
+        }
+
+        let trailing = self.trailing_comment.as_deref().filter(|_| comments);
+        write_comment(w, trailing, 0, "")
+    }
+
+    fn pp_key<W: fmt::Write>(
+        &self,
+        w: &mut W,
+        indent: usize,
+        unit: &str,
+        comments: bool,
+    ) -> fmt::Result {
+        let comment = self.comment.as_deref().filter(|_| comments);
+        write_comment(w, comment, indent - 1, unit)?;
+        for _ in 0..indent - 1 {
+            w.write_str(unit)?;
+        }
+        if let Some(quote) = self.quotes {
+            let (quote, escaped) = choose_quote(&self.key, quote);
+            w.write_char(quote.char())?;
+            w.write_str(&escaped)?;
+            w.write_char(quote.char())?;
+        } else if self.key.contains(['\n', '\r'])
+            || self.key.starts_with(['"', '\'', '\t'])
+            || self.has_escaped_comment()
+        {
+            // written bare, these would scan as something else
+            w.write_char('"')?;
+            w.write_str(&quote_unquoted(&self.key))?;
+            w.write_char('"')?;
+        } else {
+            if self.key.starts_with([self.comment_char, ' ']) {
+                w.write_char('\\')?;
+            }
+            w.write_str(&self.key)?;
+        }
+        w.write_char('\n')
+    }
+
+    // whether the key contains a backslash before the comment character,
+    // which would lose its backslash if the key were scanned again
+    fn has_escaped_comment(&self) -> bool {
+        self.key
+            .match_indices('\\')
+            .any(|(i, _)| self.key[i + 1..].starts_with(self.comment_char))
+    }
+
+    /// Parse the string including escape sequences if it's quoted.
+    ///
+    /// Operates on the first child of the node. See [`Config::child`].
+    ///
+    /// The supported escape sequences are:
+    ///
+    /// | escape       | result                                    |
+    /// |--------------|-------------------------------------------|
+    /// | `\n`         | newline                                   |
+    /// | `\r`         | carriage return                           |
+    /// | `\t`         | tab                                       |
+    /// | `\0`         | null                                      |
+    /// | `\a`         | bell, 0x07                                |
+    /// | `\b`         | backspace, 0x08                           |
+    /// | `\f`         | form feed, 0x0C                           |
+    /// | `\v`         | vertical tab, 0x0B                        |
+    /// | `\\`         | backslash                                 |
+    /// | `\"`, `\'`   | quotes                                    |
+    /// | `\u{1F600}`  | the unicode code point, in hex            |
+    /// | `\` newline  | nothing, along with following indentation |
+    ///
+    /// An unquoted value only supports escaping the comment character, as in
+    /// `my \# value`, and the backslash is already left out of
+    /// [`Config::key`]. It is returned as is.
+    pub fn parse_quoted(&self) -> Result<String, NcclError> {
+        // TODO use a library for this garbage
+        if !self.quoted() {
+            Ok(String::from(self.key()))
+        } else {
+            let mut value = Vec::with_capacity(self.key.len());
+
+            let bytes = self.key.as_bytes();
+            let mut i = 0;
+
+            while i < bytes.len() {
+                if bytes[i] == b'\\' {
+                    i += 1;
+                    if i >= bytes.len() {
+                        return Err(NcclError::DanglingEscape {
+                            line: self.line_at(i - 1),
+                        });
+                    }
+
+                    match bytes[i] {
+                        // \n
+                        b'n' => {
+                            value.push(b'\n');
+                            i += 1;
+                        }
+
+                        // \r
+                        b'r' => {
+                            value.push(b'\r');
+                            i += 1;
+                        }
+
+                        // \t
+                        b't' => {
+                            value.push(b'\t');
+                            i += 1;
+                        }
+
+                        // \0, null
+                        b'0' => {
+                            value.push(0x00);
+                            i += 1;
+                        }
+
+                        // \a, bell
+                        b'a' => {
+                            value.push(0x07);
+                            i += 1;
+                        }
+
+                        // \b, backspace
+                        b'b' => {
+                            value.push(0x08);
+                            i += 1;
+                        }
+
+                        // \f, form feed
+                        b'f' => {
+                            value.push(0x0c);
+                            i += 1;
+                        }
+
+                        // \v, vertical tab
+                        b'v' => {
+                            value.push(0x0b);
+                            i += 1;
+                        }
+
+                        // \u{1F600}
+                        b'u' => {
+                            let (code, len) = unicode_escape(&bytes[i + 1..]).ok_or(
+                                NcclError::InvalidUnicodeEscape {
+                                    line: self.line_at(i),
+                                },
+                            )?;
+                            value.extend_from_slice(code.encode_utf8(&mut [0; 4]).as_bytes());
+                            i += 1 + len;
+                        }
+
+                        // \\
+                        b'\\' => {
+                            value.push(b'\\');
+                            i += 1;
+                        }
+
+                        // \" or \'
+                        code @ (b'"' | b'\'') => {
+                            value.push(code);
+                            i += 1;
+                        }
+
+                        // something \
+                        //       more stuff
+                        b'\r' | b'\n' => {
+                            if bytes[i] == b'\r' && bytes.get(i + 1) == Some(&b'\n') {
+                                i += 1;
+                            }
+                            i += 1;
 
                             if i >= bytes.len() {
                                 return Err(NcclError::UnterminatedString {
-                                    start: self.span.line,
+                                    start: self.line_at(0),
                                 });
                             }
 
-                            while bytes[i] == b' ' || bytes[i] == b'\t' {
-                                i += 1;
+                            while bytes[i] == b' ' || bytes[i] == b'\t' {
+                                i += 1;
+
+                                if i >= bytes.len() {
+                                    return Err(NcclError::UnterminatedString {
+                                        start: self.line_at(0),
+                                    });
+                                }
+                            }
+                        }
+
+                        _ => {
+                            return Err(NcclError::ParseUnknownEscape {
+                                escape: bytes[i] as char,
+                                line: self.line_at(i),
+                            });
+                        }
+                    }
+                } else {
+                    value.push(bytes[i]);
+                    i += 1;
+                }
+            }
+
+            // the source was already valid, and escapes are validated chars
+            Ok(String::from_utf8(value).expect("escapes produce valid utf-8"))
+        }
+    }
+
+    // the span of a quoted value is where it ends, so count the lines between
+    // the byte index and the end of the value.
+    fn line_at(&self, i: usize) -> usize {
+        let lines_after = self.key.as_bytes()[i..]
+            .iter()
+            .filter(|&&byte| byte == b'\n')
+            .count();
+        self.span.line.saturating_sub(lines_after)
+    }
+
+    /// Copy the config into one which does not borrow from its source.
+    ///
+    /// ```
+    /// let config = {
+    ///     let source = String::from("key\n    value\n");
+    ///     let config = nccl::parse_config(&source).unwrap().to_owned_config();
+    ///     config
+    /// };
+    /// assert_eq!(config["key"].value(), Some("value"));
+    /// ```
+    pub fn to_owned_config(&self) -> OwnedConfig {
+        let mut node = OwnedConfig::new_with_span(String::from(self.key()), self.span, self.quotes);
+        for child in self.children() {
+            node.add_child(child.to_owned_config());
+        }
+        node
+    }
+
+    /// Resolve `${path}` references in leaf values.
+    ///
+    /// A reference is a dot-separated path of keys from the root of this
+    /// config, and is replaced by the first value of the node it names. The
+    /// result owns its strings, since resolved values may not appear anywhere
+    /// in the source.
+    ///
+    /// ```
+    /// let source = "server\n    port\n        80\nurl\n    http://localhost:${server.port}\n";
+    /// let config = nccl::parse_config(&source).unwrap();
+    /// let resolved = config.resolve().unwrap();
+    /// assert_eq!(resolved["url"].value(), Some("http://localhost:80"));
+    /// ```
+    pub fn resolve(&self) -> Result<OwnedConfig, NcclError> {
+        self.resolve_node(self, &mut Vec::new())
+    }
+
+    fn resolve_node(
+        &self,
+        root: &Config<'a>,
+        stack: &mut Vec<String>,
+    ) -> Result<OwnedConfig, NcclError> {
+        let key = if self.value.is_empty() {
+            root.interpolate(&self.key, self.span, stack)?
+        } else {
+            String::from(self.key())
+        };
+
+        let mut node = OwnedConfig::new_with_span(key, self.span, self.quotes);
+        for child in self.children() {
+            node.add_child(child.resolve_node(root, stack)?);
+        }
+
+        Ok(node)
+    }
+
+    fn interpolate(
+        &self,
+        text: &str,
+        span: Span,
+        stack: &mut Vec<String>,
+    ) -> Result<String, NcclError> {
+        let mut result = String::with_capacity(text.len());
+        let mut rest = text;
+
+        while let Some(start) = rest.find("${") {
+            let Some(end) = rest[start..].find('}').map(|end| start + end) else {
+                break;
+            };
+
+            result.push_str(&rest[..start]);
+            let reference = &rest[start + 2..end];
+
+            if stack.iter().any(|seen| seen == reference) {
+                return Err(NcclError::ReferenceCycle {
+                    reference: String::from(reference),
+                    line: span.line,
+                });
+            }
+
+            let target = reference
+                .split('.')
+                .try_fold(self, |node, key| node.value.get(key))
+                .and_then(|node| node.value())
+                .ok_or_else(|| NcclError::UnresolvedReference {
+                    reference: String::from(reference),
+                    line: span.line,
+                })?;
+
+            stack.push(String::from(reference));
+            result.push_str(&self.interpolate(target, span, stack)?);
+            stack.pop();
+
+            rest = &rest[end + 1..];
+        }
+
+        result.push_str(rest);
+        Ok(result)
+    }
+}
+
+#[cfg(feature = "std")]
+fn interpolate_env(text: &str, span: Span, strict: bool) -> Result<String, NcclError> {
+    let mut result = String::with_capacity(text.len());
+    let mut rest = text;
+
+    while let Some(start) = rest.find("${") {
+        let Some(end) = rest[start..].find('}').map(|end| start + end) else {
+            break;
+        };
+
+        result.push_str(&rest[..start]);
+        let name = &rest[start + 2..end];
+
+        match std::env::var(name) {
+            Ok(value) => result.push_str(&value),
+            Err(_) if strict => {
+                return Err(NcclError::UnresolvedReference {
+                    reference: String::from(name),
+                    line: span.line,
+                })
+            }
+            Err(_) => result.push_str(&rest[start..=end]),
+        }
+
+        rest = &rest[end + 1..];
+    }
+
+    result.push_str(rest);
+    Ok(result)
+}
+
+fn json_string(value: &str, json: &mut String) {
+    json.push('"');
+    for c in value.chars() {
+        match c {
+            '"' => json.push_str("\\\""),
+            '\\' => json.push_str("\\\\"),
+            '\n' => json.push_str("\\n"),
+            '\r' => json.push_str("\\r"),
+            '\t' => json.push_str("\\t"),
+            c if c.is_control() => json.push_str(&format!("\\u{:04x}", c as u32)),
+            c => json.push(c),
+        }
+    }
+    json.push('"');
+}
+
+// parses the {1F600} part of a unicode escape, returning the character and
+// how many bytes it took up
+fn unicode_escape(bytes: &[u8]) -> Option<(char, usize)> {
+    let hex_len = bytes
+        .iter()
+        .skip(1)
+        .take_while(|byte| byte.is_ascii_hexdigit())
+        .count();
+
+    if bytes.first() != Some(&b'{') || bytes.get(1 + hex_len) != Some(&b'}') {
+        return None;
+    }
+
+    core::str::from_utf8(&bytes[1..1 + hex_len])
+        .ok()
+        .and_then(|hex| u32::from_str_radix(hex, 16).ok())
+        .and_then(char::from_u32)
+        .map(|code| (code, hex_len + 2))
+}
+
+// writes each line of a kept comment at the given indentation
+fn write_comment<W: fmt::Write>(
+    w: &mut W,
+    comment: Option<&str>,
+    indent: usize,
+    unit: &str,
+) -> fmt::Result {
+    for line in comment.iter().flat_map(|comment| comment.lines()) {
+        for _ in 0..indent {
+            w.write_str(unit)?;
+        }
+        w.write_str(line.trim())?;
+        w.write_char('\n')?;
+    }
+    Ok(())
+}
+
+// escapes an unquoted key, which has no escape sequences of its own, so it
+// can be written in double quotes
+fn quote_unquoted(key: &str) -> String {
+    let mut escaped = String::with_capacity(key.len());
+    for c in key.chars() {
+        match c {
+            '\\' => escaped.push_str("\\\\"),
+            '"' => escaped.push_str("\\\""),
+            '\n' => escaped.push_str("\\n"),
+            '\r' => escaped.push_str("\\r"),
+            '\t' => escaped.push_str("\\t"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+// escapes a quoted key with whichever kind of quote needs fewer escapes,
+// preferring the kind it already has
+fn choose_quote(key: &str, quote: QuoteKind) -> (QuoteKind, String) {
+    let other = match quote {
+        QuoteKind::Single => QuoteKind::Double,
+        QuoteKind::Double => QuoteKind::Single,
+    };
+
+    let escaped = escape_quoted(key, quote);
+    let other_escaped = escape_quoted(key, other);
+    if other_escaped.len() < escaped.len() {
+        (other, other_escaped)
+    } else {
+        (quote, escaped)
+    }
+}
+
+// escapes anything in a quoted key that would keep it from scanning again,
+// leaving existing escape sequences alone
+fn escape_quoted(key: &str, quote: QuoteKind) -> String {
+    let bytes = key.as_bytes();
+    let mut escaped = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+
+    while i < bytes.len() {
+        match bytes[i] {
+            b'\\' => {
+                let len = match bytes.get(i + 1) {
+                    Some(
+                        b'n' | b'r' | b't' | b'0' | b'a' | b'b' | b'f' | b'v' | b'\\' | b'"'
+                        | b'\'' | b'\r' | b'\n',
+                    ) => 2,
+                    Some(b'u') => unicode_escape(&bytes[i + 2..]).map_or(0, |(_, len)| len + 2),
+                    _ => 0,
+                };
+
+                if len == 0 {
+                    escaped.extend_from_slice(b"\\\\");
+                    i += 1;
+                } else {
+                    escaped.extend_from_slice(&bytes[i..i + len]);
+                    i += len;
+                }
+            }
+
+            byte if byte == quote.char() as u8 => {
+                escaped.extend_from_slice(&[b'\\', byte]);
+                i += 1;
+            }
+
+            byte => {
+                escaped.push(byte);
+                i += 1;
+            }
+        }
+    }
+
+    String::from_utf8(escaped).expect("only ascii is inserted")
+}
+
+impl<'a> Index<&str> for Config<'a> {
+    type Output = Config<'a>;
+
+    fn index(&self, index: &str) -> &Self::Output {
+        &self.value[index]
+    }
+}
+
+impl IndexMut<&str> for Config<'_> {
+    fn index_mut(&mut self, index: &str) -> &mut Self::Output {
+        self.value.get_mut(index).expect("no such key")
+    }
+}
+
+impl fmt::Display for Config<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.pp(f, 0, None, true)
+    }
+}
+
+/// A nccl configuration which owns its keys
+///
+/// Unlike [`Config`], this does not borrow from the source it was parsed from,
+/// so it may outlive it.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct OwnedConfig {
+    pub(crate) quotes: Option<QuoteKind>,
+    pub(crate) key: String,
+    pub(crate) value: HashMap<String, OwnedConfig>,
+    pub(crate) span: Span,
+}
+
+impl OwnedConfig {
+    pub(crate) fn new_with_span(key: String, span: Span, quotes: Option<QuoteKind>) -> Self {
+        OwnedConfig {
+            quotes,
+            key,
+            value: make_map(),
+            span,
+        }
+    }
+
+    pub(crate) fn add_child(&mut self, child: OwnedConfig) {
+        self.value.insert(child.key.clone(), child);
+    }
+
+    /// Merge another config into this one.
+    ///
+    /// Behaves like [`Config::merge`], but moves the children of `other`
+    /// rather than cloning them.
+    pub fn merge(&mut self, other: OwnedConfig) {
+        for (key, child) in other.value {
+            match self.value.get_mut(&key) {
+                Some(existing) => existing.merge(child),
+                None => self.add_child(child),
+            }
+        }
+    }
+
+    /// Replace `${VAR}` in leaf values with the environment variable `VAR`.
+    ///
+    /// Variables that are not set are left as-is, unless `strict` is true in
+    /// which case an [`NcclError::UnresolvedReference`] is returned.
+    ///
+    /// ```
+    /// # use nccl::*;
+    /// std::env::set_var("NCCL_DOC_HOME", "/home/nccl");
+    /// let mut config = parse_config("dir\n    ${NCCL_DOC_HOME}/data\n")
+    ///     .unwrap()
+    ///     .to_owned_config();
+    /// config.interpolate_env(false).unwrap();
+    /// assert_eq!(config["dir"].value(), Some("/home/nccl/data"));
+    /// ```
+    #[cfg(feature = "std")]
+    pub fn interpolate_env(&mut self, strict: bool) -> Result<(), NcclError> {
+        // build the new children first so self is left unchanged on error
+        self.value = self.interpolated_children(strict)?;
+        Ok(())
+    }
+
+    #[cfg(feature = "std")]
+    fn interpolated_children(
+        &self,
+        strict: bool,
+    ) -> Result<HashMap<String, OwnedConfig>, NcclError> {
+        let mut children = make_map();
+        for child in self.children() {
+            let key = if child.value.is_empty() {
+                interpolate_env(&child.key, child.span, strict)?
+            } else {
+                child.key.clone()
+            };
+            children.insert(
+                key.clone(),
+                OwnedConfig {
+                    key,
+                    value: child.interpolated_children(strict)?,
+                    quotes: child.quotes,
+                    span: child.span,
+                },
+            );
+        }
+        Ok(children)
+    }
+
+    pub fn quoted(&self) -> bool {
+        self.quotes.is_some()
+    }
+
+    pub fn quote_kind(&self) -> Option<QuoteKind> {
+        self.quotes
+    }
+
+    /// Check whether the config has the node.
+    pub fn has_value(&self, value: &str) -> bool {
+        self.value.contains_key(value)
+    }
+
+    /// Get a child node, if it exists.
+    pub fn get(&self, key: &str) -> Option<&OwnedConfig> {
+        self.value.get(key)
+    }
+
+    /// Iterator for the children of a node.
+    pub fn children(&self) -> impl ExactSizeIterator<Item = &OwnedConfig> {
+        self.value.values()
+    }
+
+    /// The first child of the node.
+    pub fn child(&self) -> Option<&OwnedConfig> {
+        self.children().next()
+    }
+
+    /// The key of the config node.
+    pub fn key(&self) -> &str {
+        &self.key
+    }
+
+    /// The location in the source of this node.
+    pub fn span(&self) -> Span {
+        self.span
+    }
+
+    /// Iterator for the child values of a node.
+    pub fn values(&self) -> impl ExactSizeIterator<Item = &str> {
+        self.value.keys().map(String::as_str)
+    }
+
+    /// The first child value of a node.
+    pub fn value(&self) -> Option<&str> {
+        self.values().next()
+    }
+}
+
+impl IntoIterator for OwnedConfig {
+    type Item = (String, OwnedConfig);
+    type IntoIter = <HashMap<String, OwnedConfig> as IntoIterator>::IntoIter;
+
+    /// Consume the node, yielding its children and their keys in order.
+    fn into_iter(self) -> Self::IntoIter {
+        self.value.into_iter()
+    }
+}
+
+impl FromStr for OwnedConfig {
+    type Err = NcclError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(crate::parse_config(s)?.to_owned_config())
+    }
+}
+
+impl Index<&str> for OwnedConfig {
+    type Output = OwnedConfig;
+
+    fn index(&self, index: &str) -> &Self::Output {
+        &self.value[index]
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn quoted() {
+        let s = "hello\\\n   world";
+
+        assert_eq!(
+            Config::new(s, Some(QuoteKind::Single))
+                .parse_quoted()
+                .unwrap(),
+            "helloworld"
+        );
+
+        let s = "hello \\\n  world";
+        assert_eq!(
+            Config::new(s, Some(QuoteKind::Single))
+                .parse_quoted()
+                .unwrap(),
+            "hello world"
+        );
+
+        let s = "hello\\\n\tworld";
+        assert_eq!(
+            Config::new(s, Some(QuoteKind::Single))
+                .parse_quoted()
+                .unwrap(),
+            "helloworld"
+        );
+
+        let s = "hello \\\n\tworld";
+        assert_eq!(
+            Config::new(s, Some(QuoteKind::Single))
+                .parse_quoted()
+                .unwrap(),
+            "hello world"
+        );
+
+        let s = r#"\"\"\"\""#;
+        assert_eq!(
+            Config::new(s, Some(QuoteKind::Single))
+                .parse_quoted()
+                .unwrap(),
+            "\"\"\"\""
+        );
+
+        let s = r#"\'\'\'\'"#;
+        assert_eq!(
+            Config::new(s, Some(QuoteKind::Single))
+                .parse_quoted()
+                .unwrap(),
+            "''''"
+        );
+
+        let s = r#"\\\"#;
+        assert!(dbg!(Config::new(s, Some(QuoteKind::Single)).parse_quoted()).is_err());
+
+        let s = "\\\r\t";
+        assert!(dbg!(Config::new(s, Some(QuoteKind::Single)).parse_quoted()).is_err());
+    }
+
+    #[test]
+    fn single_file() {
+        let s = std::fs::read_to_string("examples/config.nccl").unwrap();
+        let mut c = Config::new(&s[0..3], None);
+        c.add_child(Config {
+            quotes: None,
+            key: s[3..6].into(),
+            value: make_map(),
+            span: Span::default(),
+            comment: None,
+            trailing_comment: None,
+            indent_unit: None,
+            comment_char: '#',
+        });
+
+        assert_eq!(
+            c,
+            Config {
+                quotes: None,
+                key: "ser".into(),
+                span: Span::default(),
+                comment: None,
+                trailing_comment: None,
+                indent_unit: None,
+                comment_char: '#',
+                value: {
+                    let mut map = make_map();
+                    map.insert("ver".into(), Config::new("ver", None));
+                    map
+                }
+            }
+        )
+    }
+
+    #[test]
+    fn multi_file() {
+        let s1 = std::fs::read_to_string("examples/config.nccl").unwrap();
+        let s2 = std::fs::read_to_string("examples/config_dos.nccl").unwrap();
+        let mut c = Config::new(&s1[0..3], None);
+        c.add_child(Config {
+            quotes: None,
+            key: s2[3..6].into(),
+            value: make_map(),
+            span: Span::default(),
+            comment: None,
+            trailing_comment: None,
+            indent_unit: None,
+            comment_char: '#',
+        });
+
+        assert_eq!(
+            c,
+            Config {
+                quotes: None,
+                key: "ser".into(),
+                span: Span::default(),
+                comment: None,
+                trailing_comment: None,
+                indent_unit: None,
+                comment_char: '#',
+                value: {
+                    let mut map = make_map();
+                    map.insert("ver".into(), Config::new("ver", None));
+                    map
+                }
+            }
+        )
+    }
+
+    #[test]
+    fn to_string() {
+        let orig_source = std::fs::read_to_string("examples/all-of-em.nccl").unwrap();
+        println!("orig\n{}", orig_source);
+        let orig_config = crate::parse_config(&orig_source).unwrap();
+        println!("{:#?}\n\n\n", orig_config);
+
+        let new_source = orig_config.to_string();
+        println!("new\n{}", new_source);
+        let new_config = crate::parse_config(&new_source).unwrap();
+        println!("{:#?}\n\n\n", new_config);
+
+        assert_eq!(new_config, orig_config);
+
+        let orig_source = "a\n    \"multiple\n    lines\"\n    'escaped\\nnewline'\n";
+        let orig_config = crate::parse_config(orig_source).unwrap();
+        let new_source = orig_config.to_string();
+        let new_config = crate::parse_config(&new_source).unwrap();
+        assert_eq!(new_config, orig_config);
+        assert_eq!(
+            new_config["a"]
+                .children()
+                .map(|value| value.parse_quoted().unwrap())
+                .collect::<Vec<_>>(),
+            vec!["multiple\n    lines", "escaped\nnewline"]
+        );
+
+        let mut orig_config = Config::new(TOP_LEVEL_KEY, None);
+        orig_config.add_child(Config::new(r#"say "hi" \o/"#, Some(QuoteKind::Double)));
+        orig_config.add_child(Config::new(r#"it's \u{41}"#, Some(QuoteKind::Single)));
+        let new_source = orig_config.to_string();
+        assert_eq!(new_source, "'say \"hi\" \\\\o/'\n\"it's \\u{41}\"\n");
+        let new_config = crate::parse_config(&new_source).unwrap();
+        assert_eq!(
+            new_config
+                .children()
+                .map(|value| value.parse_quoted().unwrap())
+                .collect::<Vec<_>>(),
+            vec![r#"say "hi" \o/"#, "it's A"]
+        );
+    }
+
+    #[test]
+    fn key() {
+        let source = "key\n value\n";
+        let config = crate::parse_config(source).unwrap();
+        assert_eq!(config["key"].key(), "key");
+        assert_eq!(config.key(), TOP_LEVEL_KEY);
+
+        let orig_source = std::fs::read_to_string("examples/all-of-em.nccl").unwrap();
+        let orig_config = crate::parse_config(&orig_source).unwrap();
+        assert_eq!(orig_config["h"]["k"].key(), "k");
+
+        let source = std::fs::read_to_string("examples/config.nccl").unwrap();
+        let config = crate::parse_config(&source).unwrap();
+        assert_eq!(
+            config.children().map(Config::key).collect::<Vec<_>>(),
+            vec!["server"]
+        );
+    }
+
+    #[test]
+    fn children_vec() {
+        let source = std::fs::read_to_string("examples/config.nccl").unwrap();
+        let config = crate::parse_config(&source).unwrap();
+        let children = config["server"].children_vec();
+        assert_eq!(children.len(), 3);
+        assert_eq!(children[0].key(), "domain");
+        assert_eq!(children[1].key(), "port");
+        assert_eq!(children[2].key(), "root");
+    }
+
+    #[test]
+    fn tab_escape() {
+        let config = crate::parse_config("\"a\\tb\"\n").unwrap();
+        assert_eq!(config.child().unwrap().parse_quoted().unwrap(), "a\tb");
+    }
+
+    #[test]
+    fn unicode_escape() {
+        let config = crate::parse_config("\"\\u{41}\"\n'\\u{1F600}'\n").unwrap();
+        assert_eq!(
+            Ok(vec![String::from("A"), String::from("😀")]),
+            config
+                .children()
+                .map(|value| value.parse_quoted())
+                .collect::<Result<Vec<_>, _>>()
+        );
+
+        assert_eq!(
+            crate::parse_config("\"\\u{zz}\"\n"),
+            Err(NcclError::InvalidUnicodeEscape { line: 1 })
+        );
+
+        for s in [
+            r"\u{zz}",
+            r"\u41",
+            r"\u{41",
+            r"\u{}",
+            r"\u{110000}",
+            r"\u{D800}",
+        ] {
+            assert_eq!(
+                Config::new(s, Some(QuoteKind::Double)).parse_quoted(),
+                Err(NcclError::InvalidUnicodeEscape { line: 0 })
+            );
+        }
+    }
+
+    #[test]
+    fn as_scalar() {
+        let long = std::fs::read_to_string("examples/long.nccl").unwrap();
+        let config = crate::parse_config(&long).unwrap();
+        assert_eq!(config["bool one"].as_bool(), Some(true));
+        assert_eq!(config["bool too"].as_bool(), Some(false));
+        assert_eq!(config["key"].as_bool(), None);
+        assert_eq!(config["ints"].as_i64(), Some(5280));
+        assert_eq!(config["ints"].as_f64(), Some(5280.0));
+
+        let source = std::fs::read_to_string("examples/config.nccl").unwrap();
+        let config = crate::parse_config(&source).unwrap();
+        assert_eq!(config["server"]["port"].as_i64(), Some(80));
+        assert_eq!(config["server"]["root"].as_i64(), None);
+        assert_eq!(config["server"]["port"]["80"].as_i64(), None);
+    }
+
+    #[test]
+    fn subtree_eq() {
+        let single = crate::parse_config("a\n    'x'\n    b\n").unwrap();
+        let double = crate::parse_config("a\n    b\n    \"x\"\n").unwrap();
+        assert!(single.subtree_eq(&double));
+        assert!(single["a"].subtree_eq(&double["a"]));
+
+        let unquoted = crate::parse_config("a\n    x\n    b\n").unwrap();
+        assert!(unquoted.subtree_eq(&double));
+
+        let other = crate::parse_config("a\n    'y'\n    b\n").unwrap();
+        assert!(!single.subtree_eq(&other));
+        assert!(!single.subtree_eq(&single["a"]));
+
+        // two children that unescape the same can't both match one child
+        let repeated = crate::parse_config("x\n\"\\u{78}\"\n").unwrap();
+        let distinct = crate::parse_config("x\ny\n").unwrap();
+        assert!(!repeated.subtree_eq(&distinct));
+        assert!(!distinct.subtree_eq(&repeated));
+        assert!(repeated.subtree_eq(&repeated.clone()));
+
+        for (a, b) in [(&single, &double), (&unquoted, &double), (&single, &other)] {
+            assert_eq!(a.subtree_eq(b), b.subtree_eq(a));
+        }
+    }
+
+    #[test]
+    fn value_at() {
+        let source = std::fs::read_to_string("examples/config.nccl").unwrap();
+        let config = crate::parse_config(&source).unwrap();
+        assert_eq!(config["server"]["port"].value_at(1), Some("443"));
+        assert_eq!(config["server"].value_at(2), Some("root"));
+        assert_eq!(config["server"]["root"].value_at(1), None);
+    }
+
+    #[test]
+    fn first_last_key() {
+        let source = std::fs::read_to_string("examples/config.nccl").unwrap();
+        let config = crate::parse_config(&source).unwrap();
+        assert_eq!(config["server"].first_key(), Some("domain"));
+        assert_eq!(config["server"].last_key(), Some("root"));
+        assert_eq!(config["server"]["root"].first_key(), Some("/var/www/html"));
+        assert_eq!(config["server"]["root"].last_key(), Some("/var/www/html"));
+        assert_eq!(Config::new("empty", None).first_key(), None);
+    }
+
+    #[test]
+    fn merge_preferring_other() {
+        let user = std::fs::read_to_string("examples/user.nccl").unwrap();
+        let default = std::fs::read_to_string("examples/default.nccl").unwrap();
+        let mut config = crate::parse_config(&default).unwrap();
+        config.merge_preferring_other(&crate::parse_config(&user).unwrap());
+
+        assert_eq!(config["beans"].value(), Some("four"));
+        assert_eq!(
+            config["beans"].values().collect::<Vec<_>>(),
+            vec!["four", "none"]
+        );
+        assert_eq!(config["frog"].value(), Some("yes"));
+        assert_eq!(config.values().collect::<Vec<_>>(), vec!["beans", "frog"]);
+
+        let mut nested = crate::parse_config("a\n    b\n        1\n    c\n").unwrap();
+        nested.merge_preferring_other(&crate::parse_config("a\n    b\n        2\n").unwrap());
+        assert_eq!(nested["a"].values().collect::<Vec<_>>(), vec!["b", "c"]);
+        assert_eq!(
+            nested["a"]["b"].values().collect::<Vec<_>>(),
+            vec!["2", "1"]
+        );
+    }
+
+    #[test]
+    fn path_to() {
+        let source = std::fs::read_to_string("examples/config.nccl").unwrap();
+        let config = crate::parse_config(&source).unwrap();
+        let target = &config["server"]["port"]["80"];
+        assert_eq!(config.path_to(target), Some(vec!["server", "port", "80"]));
+
+        let copy = target.clone();
+        assert_eq!(config.path_to(&copy), Some(vec!["server", "port", "80"]));
+
+        assert_eq!(config.path_to(&Config::new("nowhere", None)), None);
+    }
+
+    #[test]
+    fn merge_from_str() {
+        let user = std::fs::read_to_string("examples/user.nccl").unwrap();
+        let default = std::fs::read_to_string("examples/default.nccl").unwrap();
+
+        let mut config = crate::parse_config(&user).unwrap();
+        config.merge_from_str(&default).unwrap();
+        assert_eq!(
+            config["beans"].values().collect::<Vec<_>>(),
+            vec!["four", "none"]
+        );
+        assert_eq!(config["frog"].value(), Some("yes"));
+
+        let before = config.clone();
+        assert!(config.merge_from_str("a\n  b\n    c\n   d\n").is_err());
+        assert_eq!(config, before);
+    }
+
+    #[test]
+    fn interpolate_env() {
+        std::env::set_var("NCCL_TEST_INTERPOLATE_ENV", "value");
+        std::env::remove_var("NCCL_TEST_INTERPOLATE_UNSET");
+
+        let source = "a\n    b\n        x${NCCL_TEST_INTERPOLATE_ENV}y\n    c\n        ${NCCL_TEST_INTERPOLATE_UNSET}\n";
+        let mut config = crate::parse_config(source).unwrap().to_owned_config();
+        config.interpolate_env(false).unwrap();
+        assert_eq!(config["a"]["b"].value(), Some("xvaluey"));
+        assert_eq!(
+            config["a"]["c"].value(),
+            Some("${NCCL_TEST_INTERPOLATE_UNSET}")
+        );
+
+        let mut config = crate::parse_config(source).unwrap().to_owned_config();
+        assert_eq!(
+            config.interpolate_env(true),
+            Err(NcclError::UnresolvedReference {
+                reference: String::from("NCCL_TEST_INTERPOLATE_UNSET"),
+                line: 5,
+            })
+        );
+
+        // a failed strict call leaves the config unchanged
+        let source =
+            "a\n    ${NCCL_TEST_INTERPOLATE_ENV}\nb\n    ${NCCL_TEST_INTERPOLATE_UNSET}\nc\n";
+        let original = crate::parse_config(source).unwrap().to_owned_config();
+        let mut config = original.clone();
+        assert!(config.interpolate_env(true).is_err());
+        assert_eq!(config, original);
+        assert_eq!(config.values().collect::<Vec<_>>(), vec!["a", "b", "c"]);
+    }
+
+    #[test]
+    fn resolve() {
+        let source = r#"server
+    root
+        /var/www/html
+    port
+        80
+paths
+    index
+        ${server.root}/index.html
+    both
+        ${server.root}:${server.port}
+    unclosed
+        ${server.root
+"#;
+        let config = crate::parse_config(source).unwrap();
+        let resolved = config.resolve().unwrap();
+        assert_eq!(
+            resolved["paths"]["index"].value(),
+            Some("/var/www/html/index.html")
+        );
+        assert_eq!(resolved["paths"]["both"].value(), Some("/var/www/html:80"));
+        assert_eq!(resolved["paths"]["unclosed"].value(), Some("${server.root"));
+        assert_eq!(resolved["server"]["port"].value(), Some("80"));
+    }
+
+    #[test]
+    fn resolve_missing() {
+        let source = "a\n    ${server.port}\n";
+        let config = crate::parse_config(source).unwrap();
+        assert_eq!(
+            config.resolve(),
+            Err(NcclError::UnresolvedReference {
+                reference: String::from("server.port"),
+                line: 2,
+            })
+        );
+    }
+
+    #[test]
+    fn resolve_cycle() {
+        let source = "a\n    ${b}\nb\n    ${a}\n";
+        let config = crate::parse_config(source).unwrap();
+        assert!(matches!(
+            config.resolve(),
+            Err(NcclError::ReferenceCycle { .. })
+        ));
+    }
+
+    #[test]
+    fn value_as() {
+        let source = std::fs::read_to_string("examples/config.nccl").unwrap();
+        let config = crate::parse_config(&source).unwrap();
+        assert_eq!(config["server"]["port"].value_as::<u16>(), Some(Ok(80)));
+        assert!(config["server"]["root"].value_as::<u16>().unwrap().is_err());
+        assert_eq!(config["server"]["port"]["80"].value_as::<u16>(), None);
+
+        let source = std::fs::read_to_string("examples/long.nccl").unwrap();
+        let config = crate::parse_config(&source).unwrap();
+        assert_eq!(config["bool too"].value_as::<bool>(), Some(Ok(false)));
+    }
+
+    #[test]
+    fn to_string_minified() {
+        let source = std::fs::read_to_string("examples/all-of-em.nccl").unwrap();
+        let config = crate::parse_config(&source).unwrap();
+
+        let minified = config.to_string_minified();
+        assert!(minified.len() < config.to_string().len());
+        assert_eq!(crate::parse_config(&minified).unwrap(), config);
+
+        let options = crate::ParseOptions {
+            keep_comments: true,
+            ..Default::default()
+        };
+        let config = crate::parse_config_with_options("# c\na\n    # d\n    b\n", options).unwrap();
+        assert_eq!(config.to_string(), "# c\na\n    # d\n    b\n");
+        assert_eq!(config.to_string_minified(), "a\n b\n");
+    }
+
+    #[test]
+    fn values_as() {
+        let source = std::fs::read_to_string("examples/config.nccl").unwrap();
+        let config = crate::parse_config(&source).unwrap();
+        assert_eq!(
+            config["server"]["port"].values_as::<u16>(),
+            Ok(vec![80u16, 443])
+        );
+        assert!(config["server"]["domain"].values_as::<u16>().is_err());
+        assert_eq!(
+            config["server"]["port"]["80"].values_as::<u16>(),
+            Ok(vec![])
+        );
+    }
+
+    #[test]
+    fn span() {
+        let source = std::fs::read_to_string("examples/config.nccl").unwrap();
+        let config = crate::parse_config(&source).unwrap();
+        assert_eq!(config["server"].span().line, 1);
+        assert_eq!(config["server"]["port"].span().line, 5);
+        assert_eq!(config["server"]["port"]["443"].span().line, 7);
+        assert_ne!(config["server"]["port"]["443"].span().column, 0);
+    }
+
+    #[test]
+    fn depth_histogram() {
+        fn count(config: &Config) -> usize {
+            1 + config.children().map(count).sum::<usize>()
+        }
+
+        let source = std::fs::read_to_string("examples/long.nccl").unwrap();
+        let config = crate::parse_config(&source).unwrap();
+        let histogram = config.depth_histogram();
+        assert_eq!(histogram[0], 1);
+        assert_eq!(histogram.iter().sum::<usize>(), count(&config));
+
+        let source = std::fs::read_to_string("examples/config.nccl").unwrap();
+        let config = crate::parse_config(&source).unwrap();
+        assert_eq!(config.depth_histogram(), vec![1, 1, 3, 5]);
+    }
+
+    #[test]
+    fn get_path_ignore_case() {
+        let source = std::fs::read_to_string("examples/config.nccl").unwrap();
+        let config = crate::parse_config(&source).unwrap();
+        let port = config.get_path_ignore_case(&["SERVER", "PORT"]).unwrap();
+        assert_eq!(port.key(), "port");
+        assert_eq!(port.value(), Some("80"));
+        assert!(config.get_path_ignore_case(&["SERVER", "PORTS"]).is_none());
+        assert_eq!(config.get_path_ignore_case(&[]), Some(&config));
+    }
+
+    #[test]
+    fn entries() {
+        let source = std::fs::read_to_string("examples/config.nccl").unwrap();
+        let config = crate::parse_config(&source).unwrap();
+        let entries = config["server"].entries().collect::<Vec<_>>();
+        assert_eq!(
+            entries.iter().map(|(key, _)| *key).collect::<Vec<_>>(),
+            vec!["domain", "port", "root"]
+        );
+        assert_eq!(entries[1].1, &config["server"]["port"]);
+    }
+
+    #[test]
+    fn with_child() {
+        let built = Config::new(TOP_LEVEL_KEY, None)
+            .with_value("server", "localhost")
+            .with_value("ports", "80")
+            .with_value("ports", "443")
+            .with_child("empty")
+            .with_child("empty");
+
+        let source = "server\n    localhost\nports\n    80\n    443\nempty\n";
+        assert_eq!(built, crate::parse_config(source).unwrap());
+    }
+
+    #[test]
+    fn to_owned_config() {
+        let source = std::fs::read_to_string("examples/config.nccl").unwrap();
+        let config = crate::parse_config(&source).unwrap();
+        let owned = config.to_owned_config();
+        drop(config);
+        drop(source);
+
+        assert_eq!(owned["server"]["root"].value(), Some("/var/www/html"));
+        assert_eq!(
+            owned.get("server").unwrap()["port"]
+                .values()
+                .collect::<Vec<_>>(),
+            vec!["80", "443"]
+        );
+        assert_eq!(
+            owned["server"]
+                .children()
+                .map(OwnedConfig::key)
+                .collect::<Vec<_>>(),
+            vec!["domain", "port", "root"]
+        );
+        assert!(owned.get("client").is_none());
+    }
+
+    #[test]
+    fn insert_child() {
+        let mut server = Config::new("server", None);
+        server.insert_child("domain");
+        server.insert_child("root");
+        server.insert_child("root");
+
+        let mut config = Config::new(TOP_LEVEL_KEY, None);
+        config.add_child(server);
+        config.insert_child("hello");
+
+        let source = config.to_string();
+        assert_eq!(source, "server\n    domain\n    root\nhello\n");
+        assert_eq!(crate::parse_config(&source).unwrap(), config);
+    }
+
+    #[test]
+    fn insert_child_needing_quotes() {
+        for key in [
+            "two\nlines",
+            "carriage\rreturn",
+            "\"quoted\"",
+            "'single'",
+            "\tindented",
+            "\"both\\n\"\n",
+        ] {
+            let mut config = Config::new(TOP_LEVEL_KEY, None);
+            config.entry("parent").entry(key).insert_child("child");
+
+            let source = config.to_string();
+            let reparsed = crate::parse_config(&source).unwrap();
+            let parent = &reparsed["parent"];
+            assert_eq!(parent.len(), 1, "{:?}", source);
+            let node = parent.child().unwrap();
+            assert_eq!(node.parse_quoted().unwrap(), key, "{:?}", source);
+            assert_eq!(node.values().collect::<Vec<_>>(), vec!["child"]);
+        }
+    }
+
+    #[test]
+    fn remove() {
+        let source = std::fs::read_to_string("examples/config.nccl").unwrap();
+        let mut config = crate::parse_config(&source).unwrap();
+
+        let port = config["server"].clone().remove("port").unwrap();
+        assert_eq!(port.values().collect::<Vec<_>>(), vec!["80", "443"]);
+
+        let server = config.remove("server").unwrap();
+        assert_eq!(server.key(), "server");
+        assert!(!config.has_value("server"));
+        assert!(config.remove("server").is_none());
+
+        let mut server = server;
+        server.remove("domain");
+        assert_eq!(
+            server.children().map(Config::key).collect::<Vec<_>>(),
+            vec!["port", "root"]
+        );
+    }
+
+    #[test]
+    fn len() {
+        let source = std::fs::read_to_string("examples/config.nccl").unwrap();
+        let config = crate::parse_config(&source).unwrap();
+        assert_eq!(config["server"].len(), 3);
+        assert!(!config["server"].is_empty());
+        assert_eq!(config["server"]["root"]["/var/www/html"].len(), 0);
+        assert!(config["server"]["root"]["/var/www/html"].is_empty());
+    }
+
+    #[test]
+    fn parse_quoted_line() {
+        // the scanner rejects bad escapes, so build the values by hand. spans
+        // of quoted values are where they end.
+        let span = Span {
+            line: 4,
+            column: 3,
+            ..Default::default()
+        };
+
+        let value = Config::new_with_span("first\\\n\\q".into(), span, Some(QuoteKind::Double));
+        assert_eq!(
+            value.parse_quoted(),
+            Err(NcclError::ParseUnknownEscape {
+                escape: 'q',
+                line: 4,
+            })
+        );
+
+        let value = Config::new_with_span("\\q\\\nsecond".into(), span, Some(QuoteKind::Double));
+        assert_eq!(
+            value.parse_quoted(),
+            Err(NcclError::ParseUnknownEscape {
+                escape: 'q',
+                line: 3,
+            })
+        );
+
+        let value = Config::new_with_span("first\\\n\\".into(), span, Some(QuoteKind::Double));
+        assert_eq!(
+            value.parse_quoted(),
+            Err(NcclError::DanglingEscape { line: 4 })
+        );
+    }
+
+    #[test]
+    fn index_mut() {
+        let source = std::fs::read_to_string("examples/config.nccl").unwrap();
+        let mut config = crate::parse_config(&source).unwrap();
+        config["server"]["port"].insert_child("8080");
+        config["server"].remove("domain");
+
+        assert_eq!(
+            config.to_string(),
+            "server\n    port\n        80\n        443\n        8080\n    root\n        /var/www/html\n"
+        );
+    }
+
+    #[test]
+    fn merge() {
+        let mut user = Config::new(TOP_LEVEL_KEY, None)
+            .with_value("beans", "four")
+            .with_child("toast");
+        let default = Config::new(TOP_LEVEL_KEY, None)
+            .with_value("frog", "yes")
+            .with_value("beans", "none")
+            .with_value("beans", "four");
+        user.merge(&default);
+
+        let user_source = "beans\n    four\ntoast\n";
+        let default_source = "frog\n    yes\nbeans\n    none\n    four\n";
+        let parsed = crate::parse_config(user_source).unwrap();
+        let parsed = crate::parse_config_with(&parsed, default_source).unwrap();
+
+        assert_eq!(user, parsed);
+        assert_eq!(
+            user["beans"].values().collect::<Vec<_>>(),
+            vec!["four", "none"]
+        );
+
+        let sc = std::fs::read_to_string("examples/inherit.nccl").unwrap();
+        let uc = std::fs::read_to_string("examples/inherit2.nccl").unwrap();
+        let mut schema = crate::parse_config(&sc).unwrap();
+        let parsed = crate::parse_config_with(&schema, &uc).unwrap();
+        schema.merge(&crate::parse_config(&uc).unwrap());
+        assert_eq!(schema, parsed);
+    }
+
+    #[test]
+    fn walk() {
+        let source = std::fs::read_to_string("examples/config.nccl").unwrap();
+        let config = crate::parse_config(&source).unwrap();
+
+        let mut count = 0;
+        let mut deepest = 0;
+        config.walk(|depth, _| {
+            count += 1;
+            deepest = deepest.max(depth);
+        });
+        assert_eq!(count, 10);
+        assert_eq!(deepest, 3);
+
+        let mut leaves = Vec::new();
+        config["server"].walk(|depth, node| {
+            if node.is_empty() {
+                leaves.push((depth, node.key()));
+            }
+        });
+        assert_eq!(
+            leaves,
+            vec![
+                (2, "example.com"),
+                (2, "www.example.com"),
+                (2, "80"),
+                (2, "443"),
+                (2, "/var/www/html"),
+            ]
+        );
+    }
+
+    #[test]
+    fn flatten() {
+        let source = std::fs::read_to_string("examples/config.nccl").unwrap();
+        let config = crate::parse_config(&source).unwrap();
+        let flat = config.flatten();
+        assert_eq!(flat.len(), 5);
+        assert_eq!(
+            flat.iter()
+                .filter(|(path, _)| path == "server.port")
+                .map(|(_, value)| *value)
+                .collect::<Vec<_>>(),
+            vec!["80", "443"]
+        );
+        assert!(flat.contains(&(String::from("server.root"), "/var/www/html")));
+
+        assert_eq!(
+            config["server"].flatten_with("/")[0],
+            (String::from("domain"), "example.com")
+        );
+    }
+
+    #[test]
+    fn pretty_print_with() {
+        let source = std::fs::read_to_string("examples/config.nccl").unwrap();
+        let config = crate::parse_config(&source).unwrap();
+
+        let tabs = config.pretty_print_with("\t").unwrap();
+        assert!(tabs.contains("\n\t\t80\n"));
+        assert_eq!(crate::parse_config(&tabs).unwrap(), config);
+
+        let two = config.pretty_print_with("  ").unwrap();
+        assert!(two.contains("\n    80\n"));
+        assert_eq!(crate::parse_config(&two).unwrap(), config);
+
+        for indent in ["", " \t", "\t ", "--"] {
+            assert_eq!(
+                config.pretty_print_with(indent),
+                Err(NcclError::InvalidPrintIndent {
+                    indent: String::from(indent)
+                })
+            );
+        }
+    }
+
+    #[test]
+    fn deep_walk_and_print() {
+        const DEPTH: usize = 50_000;
+
+        let mut config = Config::new("a", None);
+        for _ in 1..DEPTH {
+            let mut parent = Config::new("a", None);
+            parent.add_child(config);
+            config = parent;
+        }
+
+        let mut count = 0;
+        let mut deepest = 0;
+        config.walk(|depth, _| {
+            count += 1;
+            deepest = deepest.max(depth);
+        });
+        assert_eq!(count, DEPTH);
+        assert_eq!(deepest, DEPTH - 1);
+
+        struct Lines(usize);
+        impl fmt::Write for Lines {
+            fn write_str(&mut self, s: &str) -> fmt::Result {
+                self.0 += s.matches('\n').count();
+                Ok(())
+            }
+        }
+        // the output is quadratic in the depth, so only print the bottom of it
+        let mut node = &config;
+        for _ in 0..DEPTH - 10_000 {
+            node = node.child().unwrap();
+        }
+        let mut lines = Lines(0);
+        node.pp(&mut lines, 1, Some(""), false).unwrap();
+        assert_eq!(lines.0, 10_000);
+
+        // dropping doesn't recurse either
+        drop(config);
+    }
+
+    #[test]
+    fn display() {
+        let source = std::fs::read_to_string("examples/config.nccl").unwrap();
+        let config = crate::parse_config(&source).unwrap();
+        assert_eq!(format!("{config}"), config.pretty_print());
+        assert_eq!(format!("{}", config["server"]["root"]), "/var/www/html\n");
+        assert_eq!(crate::parse_config(&format!("{config}")).unwrap(), config);
+    }
+
+    #[test]
+    fn from_str() {
+        let config: OwnedConfig = "key\n    value".parse().unwrap();
+        assert_eq!(config["key"].value(), Some("value"));
+        assert_eq!(config.key(), TOP_LEVEL_KEY);
+
+        assert!("'unterminated".parse::<OwnedConfig>().is_err());
+    }
+
+    #[test]
+    fn find_all() {
+        let source = r#"http
+    enabled
+        true
+    tls
+        enabled
+            false
+ftp
+    enabled
+        false
+enabled
+"#;
+        let config = crate::parse_config(source).unwrap();
+        let found = config.find_all("enabled");
+        assert_eq!(found.len(), 4);
+        assert_eq!(
+            found.iter().map(|node| node.value()).collect::<Vec<_>>(),
+            vec![Some("true"), Some("false"), Some("false"), None]
+        );
+        assert_eq!(found[1].span().line, 5);
+
+        assert_eq!(config["http"].find_all("enabled").len(), 2);
+        assert!(config.find_all("disabled").is_empty());
+    }
+
+    #[test]
+    fn child_value() {
+        let source = std::fs::read_to_string("examples/config.nccl").unwrap();
+        let config = crate::parse_config(&source).unwrap();
+        assert_eq!(config["server"].child_value("root"), Some("/var/www/html"));
+        assert_eq!(config["server"].child_value("port"), Some("80"));
+        assert_eq!(config["server"].child_value("client"), None);
+        assert_eq!(config["server"]["root"].child_value("/var/www/html"), None);
+    }
+
+    #[test]
+    fn owned_into_iter() {
+        let config: OwnedConfig = std::fs::read_to_string("examples/long.nccl")
+            .unwrap()
+            .parse()
+            .unwrap();
 
-                                if i >= bytes.len() {
-                                    return Err(NcclError::UnterminatedString {
-                                        start: self.span.line,
-                                    });
-                                }
-                            }
-                        }
+        let entries = config.into_iter().collect::<Vec<_>>();
+        assert_eq!(entries[0].0, "key");
+        assert_eq!(entries[0].1.value(), Some("value"));
+        assert_eq!(entries.last().unwrap().0, "indentation?");
 
-                        _ => {
-                            return Err(NcclError::ParseUnknownEscape {
-                                escape: bytes[i] as char,
-                            });
-                        }
-                    }
-                } else {
-                    value.push(bytes[i]);
-                    i += 1;
-                }
-            }
+        let (_, ints) = entries.into_iter().find(|(key, _)| key == "ints").unwrap();
+        assert_eq!(
+            ints.into_iter().map(|(key, _)| key).collect::<Vec<_>>(),
+            vec!["5280", "thirteen", "1738"]
+        );
+    }
 
-            Ok(String::from_utf8(value)?)
-        }
+    #[test]
+    fn control_escapes() {
+        let source = r#""\0" '\a' "\b" '\f' "\v" 'x\0y' "#.replace(' ', "\n");
+        let config = crate::parse_config(&source).unwrap();
+        assert_eq!(
+            config
+                .children()
+                .map(|value| value.parse_quoted().unwrap())
+                .collect::<Vec<_>>(),
+            vec!["\0", "\x07", "\x08", "\x0c", "\x0b", "x\0y"]
+        );
     }
-}
 
-impl<'a> Index<&str> for Config<'a> {
-    type Output = Config<'a>;
+    #[test]
+    fn sorted_children() {
+        let source = std::fs::read_to_string("examples/config.nccl").unwrap();
+        let config = crate::parse_config(&source).unwrap();
+        assert_eq!(
+            config["server"]
+                .sorted_children()
+                .iter()
+                .map(|child| child.key())
+                .collect::<Vec<_>>(),
+            vec!["domain", "port", "root"]
+        );
 
-    fn index(&self, index: &str) -> &Self::Output {
-        &self.value[index]
+        let source = std::fs::read_to_string("examples/long.nccl").unwrap();
+        let config = crate::parse_config(&source).unwrap();
+        let sorted = config["ints"].sorted_children();
+        assert_eq!(
+            sorted.iter().map(|child| child.key()).collect::<Vec<_>>(),
+            vec!["1738", "5280", "thirteen"]
+        );
+        assert_eq!(config["ints"].value(), Some("5280"));
     }
-}
 
-impl ToString for Config<'_> {
-    fn to_string(&self) -> String {
-        self.pretty_print()
+    #[test]
+    fn to_json() {
+        let source = std::fs::read_to_string("examples/config.nccl").unwrap();
+        let config = crate::parse_config(&source).unwrap();
+        assert_eq!(
+            config.to_json(),
+            r#"{"server":{"domain":["example.com","www.example.com"],"port":["80","443"],"root":"/var/www/html"}}"#
+        );
+        let json = serde_json::from_str::<serde_json::Value>(&config.to_json()).unwrap();
+        assert_eq!(json["server"]["root"], "/var/www/html");
+
+        let source =
+            "a\n    \"say \\\"hi\\\"\"\n    b\n        c\n    'tab\\there'\n        'a\\\\b'\n";
+        let config = crate::parse_config(source).unwrap();
+        assert_eq!(
+            config.to_json(),
+            r#"{"a":{"say \"hi\"":"say \"hi\"","b":"c","tab\there":"a\\b"}}"#
+        );
+        let json = serde_json::from_str::<serde_json::Value>(&config.to_json()).unwrap();
+        assert_eq!(json["a"]["say \"hi\""], "say \"hi\"");
+        assert_eq!(json["a"]["tab\there"], "a\\b");
+        assert_eq!(
+            Config::new("\\q", Some(QuoteKind::Single)).to_json(),
+            r#""\\q""#
+        );
+
+        assert_eq!(crate::parse_config("").unwrap().to_json(), "{}");
+        assert_eq!(
+            crate::parse_config("a\nb\n").unwrap().to_json(),
+            r#"{"a":"a","b":"b"}"#
+        );
+        assert_eq!(
+            crate::parse_config("a\n").unwrap().to_json(),
+            r#"{"a":"a"}"#
+        );
+        assert_eq!(
+            Config::new("tab\there\u{1}", None).to_json(),
+            r#""tab\there\u0001""#
+        );
     }
-}
 
-#[cfg(test)]
-mod test {
-    use super::*;
+    #[test]
+    fn has_path() {
+        let source = std::fs::read_to_string("examples/config.nccl").unwrap();
+        let config = crate::parse_config(&source).unwrap();
+        assert!(config.has_path(["server", "port", "80"]));
+        assert!(!config.has_path(["server", "port", "8080"]));
+        assert!(config.has_path([]));
+    }
 
     #[test]
-    fn quoted() {
-        let s = "hello\\\n   world";
+    fn entry() {
+        let mut config = Config::new("__top_level__", None);
+        config.entry("server").entry("port").entry("80");
+        config.entry("server").entry("port").entry("443");
+        config.entry("server").entry("root").entry("/var/www/html");
 
+        assert!(config.has_path(["server", "port", "80"]));
         assert_eq!(
-            Config::new(s, Some(QuoteKind::Single))
-                .parse_quoted()
-                .unwrap(),
-            "helloworld"
+            config["server"]["port"].values().collect::<Vec<_>>(),
+            vec!["80", "443"]
         );
+        assert_eq!(config["server"].len(), 2);
+        assert_eq!(config["server"]["root"].value(), Some("/var/www/html"));
+    }
 
-        let s = "hello \\\n  world";
+    #[test]
+    fn parse_quoted_crlf_continuation() {
+        let source = "key\r\n    \"hello \\\r\n        world\"\r\nnext\r\n    value\r\n";
+        let config = crate::parse_config(source).unwrap();
         assert_eq!(
-            Config::new(s, Some(QuoteKind::Single))
-                .parse_quoted()
-                .unwrap(),
+            config["key"].child().unwrap().parse_quoted().unwrap(),
             "hello world"
         );
+        assert_eq!(config["next"].span().line, 4);
+    }
 
-        let s = "hello\\\n\tworld";
+    #[test]
+    fn depth() {
+        let source = std::fs::read_to_string("examples/config.nccl").unwrap();
+        let config = crate::parse_config(&source).unwrap();
+        assert_eq!(config["server"].depth(), 2);
+        assert_eq!(config.depth(), 3);
+        assert_eq!(config["server"]["port"]["80"].depth(), 0);
+    }
+
+    #[test]
+    fn eq_order() {
+        let a = crate::parse_config("a\n    1\n    2\nb\n    3\n").unwrap();
+        let b = crate::parse_config("b\n    3\na\n    2\n    1\n").unwrap();
+        let c = crate::parse_config("a\n    1\n    2\nb\n    3\n    4\n").unwrap();
+
+        assert_eq!(a, b);
+        assert!(!a.eq_ordered(&b));
+        assert!(a.eq_ordered(&a.clone()));
+        assert_ne!(a, c);
+        assert!(!a.eq_ordered(&c));
+    }
+
+    #[test]
+    fn exact_size() {
+        let source = std::fs::read_to_string("examples/config.nccl").unwrap();
+        let config = crate::parse_config(&source).unwrap();
+        assert_eq!(config["server"].values().len(), 3);
+        assert_eq!(config["server"].children().len(), 3);
+
+        let owned = config.to_owned_config();
+        assert_eq!(owned["server"]["port"].values().len(), 2);
+    }
+
+    #[test]
+    fn values_owned_parsed() {
+        let source = "mixed\n    plain\n    \"tab\\there\"\n    'it\\'s'\n".to_string();
+        let config = crate::parse_config(&source).unwrap();
+
+        let owned = config["mixed"].values_owned();
+        let parsed = config["mixed"].values_parsed().unwrap();
+        drop(config);
+        drop(source);
+
+        assert_eq!(owned, vec!["plain", "tab\\there", "it\\'s"]);
+        assert_eq!(parsed, vec!["plain", "tab\there", "it's"]);
+    }
+
+    #[test]
+    fn pp_chooses_quote() {
+        let mut config = Config::new("__top_level__", None);
+        config.entry("quote").add_child(Config::new(
+            "say \"hi\" and \"bye\", it's",
+            Some(QuoteKind::Double),
+        ));
+        config
+            .entry("fine")
+            .add_child(Config::new("it's", Some(QuoteKind::Double)));
+
+        let printed = config.to_string();
         assert_eq!(
-            Config::new(s, Some(QuoteKind::Single))
-                .parse_quoted()
-                .unwrap(),
-            "helloworld"
+            printed,
+            "quote\n    'say \"hi\" and \"bye\", it\\'s'\nfine\n    \"it's\"\n"
         );
 
-        let s = "hello \\\n\tworld";
+        let reparsed = crate::parse_config(&printed).unwrap();
         assert_eq!(
-            Config::new(s, Some(QuoteKind::Single))
-                .parse_quoted()
-                .unwrap(),
-            "hello world"
+            reparsed["quote"].child().unwrap().parse_quoted().unwrap(),
+            "say \"hi\" and \"bye\", it's"
         );
+        assert_eq!(
+            reparsed["fine"].child().unwrap().parse_quoted().unwrap(),
+            "it's"
+        );
+    }
 
-        let s = r#"\"\"\"\""#;
+    #[test]
+    fn get_all() {
+        // identical sibling keys merge rather than being dropped
+        let source = "port\n    80\n        tcp\n    80\n        udp\n    443\n";
+        let config = crate::parse_config(source).unwrap();
+        let all = config["port"].get_all("80").collect::<Vec<_>>();
+        assert_eq!(all.len(), 1);
+        assert_eq!(all[0].values().collect::<Vec<_>>(), vec!["tcp", "udp"]);
+        assert_eq!(config["port"].get_all("8080").count(), 0);
+
+        let options = crate::ParseOptions {
+            duplicate_keys: crate::DuplicatePolicy::Error,
+            ..Default::default()
+        };
+        assert!(matches!(
+            crate::parse_config_with_options(source, options),
+            Err(NcclError::DuplicateKey { .. })
+        ));
+
+        let source = "key\n    \"a\\u{62}\"\n    ab\n    \"a\\\\b\"\n";
+        let config = crate::parse_config(source).unwrap();
         assert_eq!(
-            Config::new(s, Some(QuoteKind::Single))
-                .parse_quoted()
-                .unwrap(),
-            "\"\"\"\""
+            config["key"]
+                .get_all("ab")
+                .map(|child| child.key())
+                .collect::<Vec<_>>(),
+            vec!["a\\u{62}", "ab"]
         );
+    }
 
-        let s = r#"\'\'\'\'"#;
+    #[test]
+    fn keep_comments() {
+        let source = "# server settings\n# more\nserver\n    # the port\n    port\n        80\n    root\n        /var/www\n";
+        let options = crate::ParseOptions {
+            keep_comments: true,
+            ..Default::default()
+        };
+
+        let config = crate::parse_config_with_options(source, options).unwrap();
         assert_eq!(
-            Config::new(s, Some(QuoteKind::Single))
-                .parse_quoted()
-                .unwrap(),
-            "''''"
+            config["server"].comment(),
+            Some("# server settings\n# more")
         );
+        assert_eq!(config["server"]["port"].comment(), Some("# the port"));
+        assert_eq!(config["server"]["root"].comment(), None);
+        assert_eq!(config.to_string(), source);
 
-        let s = r#"\\\"#;
-        assert!(dbg!(Config::new(s, Some(QuoteKind::Single)).parse_quoted()).is_err());
+        let config = crate::parse_config(source).unwrap();
+        assert_eq!(config["server"].comment(), None);
+        assert!(!config.to_string().contains('#'));
 
-        let s = "\\\r\t";
-        assert!(dbg!(Config::new(s, Some(QuoteKind::Single)).parse_quoted()).is_err());
+        let source = "port\n    80\n# the end\n# really\n";
+        let config = crate::parse_config_with_options(source, options).unwrap();
+        assert_eq!(config.trailing_comment(), Some("# the end\n# really"));
+        assert_eq!(config.to_string(), source);
+        let config = crate::parse_config_with_options("port\n    80\n    # the end\n", options);
+        assert_eq!(config.unwrap().trailing_comment(), Some("# the end"));
+        assert_eq!(
+            crate::parse_config(source).unwrap().trailing_comment(),
+            None
+        );
+
+        let source = "# first\nport\n    80\n# second\nport\n    443\nhost\n# third\nhost\n";
+        let config = crate::parse_config_with_options(source, options).unwrap();
+        assert_eq!(config["port"].comment(), Some("# first\n# second"));
+        assert_eq!(config["host"].comment(), Some("# third"));
+        assert_eq!(
+            config.to_string(),
+            "# first\n# second\nport\n    80\n    443\n# third\nhost\n"
+        );
     }
 
     #[test]
-    fn single_file() {
-        let s = std::fs::read_to_string("examples/config.nccl").unwrap();
-        let mut c = Config::new(&s[0..3], None);
-        c.add_child(Config {
-            quotes: None,
-            key: &s[3..6],
-            value: make_map(),
-            span: Span::default(),
-        });
+    fn merge_policy() {
+        let base =
+            crate::parse_config("server\n    port\n        80\n    root\n        /var/www\n")
+                .unwrap();
+        let other =
+            crate::parse_config("server\n    port\n        8080\n    name\n        web\n").unwrap();
 
+        let merged = |policy| {
+            let mut config = base.clone();
+            config.merge_with_policy(&other, policy);
+            config
+        };
+
+        let append = merged(MergePolicy::Append);
         assert_eq!(
-            c,
-            Config {
-                quotes: None,
-                key: "ser",
-                span: Span::default(),
-                value: {
-                    let mut map = make_map();
-                    map.insert("ver", Config::new("ver", None));
-                    map
-                }
-            }
+            append["server"]["port"].values().collect::<Vec<_>>(),
+            vec!["80", "8080"]
+        );
+        let mut plain = base.clone();
+        plain.merge(&other);
+        assert!(plain.eq_ordered(&append));
+
+        let replace = merged(MergePolicy::ReplaceLeaf);
+        assert_eq!(
+            replace["server"]["port"].values().collect::<Vec<_>>(),
+            vec!["8080"]
+        );
+
+        let keep = merged(MergePolicy::KeepExisting);
+        assert_eq!(
+            keep["server"]["port"].values().collect::<Vec<_>>(),
+            vec!["80"]
+        );
+
+        for config in [append, replace, keep] {
+            assert_eq!(config["server"]["root"].value(), Some("/var/www"));
+            assert_eq!(config["server"]["name"].value(), Some("web"));
+        }
+    }
+
+    #[test]
+    fn merge_comments() {
+        let options = crate::ParseOptions {
+            keep_comments: true,
+            ..Default::default()
+        };
+        let base = crate::parse_config_with_options(
+            "# base server\nserver\n    port\n        80\n    root\n        /var/www\n",
+            options,
         )
+        .unwrap();
+        let other = crate::parse_config_with_options(
+            "# other server\nserver\n    # other port\n    port\n        8080\n    # other name\n    name\n        web\n",
+            options,
+        )
+        .unwrap();
+
+        let mut merged = base.clone();
+        merged.merge(&other);
+        assert_eq!(merged["server"].comment(), Some("# base server"));
+        assert_eq!(merged["server"]["port"].comment(), Some("# other port"));
+        assert_eq!(merged["server"]["name"].comment(), Some("# other name"));
+
+        let mut merged = base.clone();
+        merged.merge_preferring_other(&other);
+        assert_eq!(merged["server"].comment(), Some("# other server"));
+        assert_eq!(merged["server"]["port"].comment(), Some("# other port"));
+
+        let mut merged = base.clone();
+        merged.merge_comments(&other, CommentMergePolicy::KeepExisting);
+        assert_eq!(merged["server"].comment(), Some("# base server"));
+        assert_eq!(merged["server"]["port"].comment(), Some("# other port"));
+        assert!(!merged["server"].has_value("name"));
+        assert!(merged["server"]["port"].has_value("80"));
+
+        let mut merged = base.clone();
+        merged.merge_comments(&other, CommentMergePolicy::PreferOther);
+        assert_eq!(merged["server"].comment(), Some("# other server"));
+        assert_eq!(merged["server"]["root"].comment(), None);
     }
 
     #[test]
-    fn multi_file() {
-        let s1 = std::fs::read_to_string("examples/config.nccl").unwrap();
-        let mut c = Config::new(&s1[0..3], None);
+    fn count_leaves() {
+        let source = std::fs::read_to_string("examples/config.nccl").unwrap();
+        let config = crate::parse_config(&source).unwrap();
+        assert_eq!(config.count_leaves(), 5);
+        assert_eq!(config["server"]["port"].count_leaves(), 2);
+        assert_eq!(config["server"]["root"]["/var/www/html"].count_leaves(), 0);
 
-        let s2 = std::fs::read_to_string("examples/config_dos.nccl").unwrap();
-        c.add_child(Config {
-            quotes: None,
-            key: &s2[3..6],
-            value: make_map(),
-            span: Span::default(),
-        });
+        let empty = crate::parse_config("").unwrap();
+        assert_eq!(empty.count_leaves(), 0);
+        assert_eq!(empty.count_leaves(), empty.iter_leaves().count());
+        assert_eq!(config.count_leaves(), config.iter_leaves().count());
+    }
+
+    #[test]
+    fn is_leaf() {
+        let source = std::fs::read_to_string("examples/config.nccl").unwrap();
+        let config = crate::parse_config(&source).unwrap();
+        assert!(config["server"]["root"]["/var/www/html"].is_leaf());
+        assert!(!config["server"].is_leaf());
+    }
+
+    #[test]
+    fn rename_key() {
+        let source = std::fs::read_to_string("examples/config.nccl").unwrap();
+        let mut config = crate::parse_config(&source).unwrap();
+        config.insert_child("other");
+        let server = config["server"].clone();
+
+        assert!(config.rename_key("server", "http"));
+        assert!(!config.has_value("server"));
+        assert_eq!(config.values().collect::<Vec<_>>(), vec!["http", "other"]);
+        assert_eq!(config["http"].key(), "http");
+        assert!(config["http"]
+            .children()
+            .zip(server.children())
+            .all(|(a, b)| a.eq_ordered(b)));
+        assert_eq!(config["http"]["root"].value(), Some("/var/www/html"));
+
+        assert!(!config.rename_key("server", "web"));
+        assert!(!config.rename_key("http", "other"));
+        assert_eq!(config.values().collect::<Vec<_>>(), vec!["http", "other"]);
+    }
+
+    #[test]
+    fn iter_leaves() {
+        let source = std::fs::read_to_string("examples/config.nccl").unwrap();
+        let config = crate::parse_config(&source).unwrap();
+        let leaves = config.iter_leaves().collect::<Vec<_>>();
+        assert_eq!(leaves.len(), 5);
+        assert!(leaves.contains(&(vec!["server", "root"], "/var/www/html")));
+        assert_eq!(
+            leaves
+                .iter()
+                .map(|(path, value)| (path.join("."), *value))
+                .collect::<Vec<_>>(),
+            config.flatten()
+        );
+    }
 
+    #[test]
+    fn dangling_escape() {
+        let value = Config::new_with_span(
+            "ends with \\".into(),
+            Span {
+                line: 2,
+                ..Default::default()
+            },
+            Some(QuoteKind::Single),
+        );
         assert_eq!(
-            c,
-            Config {
-                quotes: None,
-                key: "ser",
-                span: Span::default(),
-                value: {
-                    let mut map = make_map();
-                    map.insert("ver", Config::new("ver", None));
-                    map
-                }
-            }
-        )
+            value.parse_quoted(),
+            Err(NcclError::DanglingEscape { line: 2 })
+        );
+
+        let value = Config::new_with_span("\\\\".into(), Span::default(), Some(QuoteKind::Double));
+        assert_eq!(value.parse_quoted().unwrap(), "\\");
     }
 
     #[test]
-    fn to_string() {
-        let orig_source = std::fs::read_to_string("examples/all-of-em.nccl").unwrap();
-        println!("orig\n{}", orig_source);
-        let orig_config = crate::parse_config(&orig_source).unwrap();
-        println!("{:#?}\n\n\n", orig_config);
+    fn children_named() {
+        let source = std::fs::read_to_string("examples/config.nccl").unwrap();
+        let config = crate::parse_config(&source).unwrap();
+        assert_eq!(
+            config["server"]["port"]
+                .children_named(|key| key.starts_with('4'))
+                .map(Config::key)
+                .collect::<Vec<_>>(),
+            vec!["443"]
+        );
+        assert_eq!(config["server"].children_named(|_| false).count(), 0);
+    }
 
-        let new_source = orig_config.to_string();
-        println!("new\n{}", new_source);
-        let new_config = crate::parse_config(&new_source).unwrap();
-        println!("{:#?}\n\n\n", new_config);
+    #[test]
+    fn glob() {
+        let source = std::fs::read_to_string("examples/config.nccl").unwrap();
+        let config = crate::parse_config(&source).unwrap();
 
-        assert_eq!(new_config, orig_config);
+        fn keys<'a>(nodes: Vec<&'a Config>) -> Vec<&'a str> {
+            nodes.iter().map(|node| node.key()).collect()
+        }
+
+        assert_eq!(
+            keys(config.glob("server.*")),
+            vec!["domain", "port", "root"]
+        );
+        assert_eq!(keys(config.glob("**.443")), vec!["443"]);
+        assert_eq!(keys(config.glob("server.*.80")), vec!["80"]);
+        assert_eq!(
+            keys(config.glob("server.**.*")),
+            vec![
+                "domain",
+                "port",
+                "root",
+                "example.com",
+                "www.example.com",
+                "80",
+                "443",
+                "/var/www/html"
+            ]
+        );
+        assert!(config.glob("server.*.8080").is_empty());
+        assert!(config.glob("nope.**").is_empty());
+
+        // every node once, however many ways `**` can reach it
+        let mut nodes = 0;
+        config.walk(|_, _| nodes += 1);
+        assert_eq!(config.glob("**").len(), nodes);
+        assert_eq!(config.glob("**.**.**").len(), nodes);
     }
 
     #[test]
-    fn key() {
-        let source = "key\n value\n";
+    fn value_or() {
+        let source = std::fs::read_to_string("examples/config.nccl").unwrap();
         let config = crate::parse_config(&source).unwrap();
-        assert_eq!(config["key"].key(), "key");
-        assert_eq!(config.key(), TOP_LEVEL_KEY);
+        assert_eq!(config["server"]["root"].value_or("/srv"), "/var/www/html");
+        assert_eq!(config["server"]["port"]["80"].value_or("none"), "none");
+        assert_eq!(
+            config["server"].child_value_or("root", "/srv"),
+            "/var/www/html"
+        );
+        assert_eq!(
+            config["server"].child_value_or("user", "www-data"),
+            "www-data"
+        );
+    }
 
-        let orig_source = std::fs::read_to_string("examples/all-of-em.nccl").unwrap();
-        let orig_config = crate::parse_config(&orig_source).unwrap();
-        assert_eq!(orig_config["h"]["k"].key(), "k");
+    #[test]
+    fn preserve_indentation() {
+        let source = std::fs::read_to_string("examples/good-tabs.nccl").unwrap();
+        let config = crate::parse_config(&source).unwrap();
+        assert_eq!(config.to_string(), format!("{}\n", source.trim()));
+        assert_eq!(config.pretty_print(), format!("{}\n", source.trim()));
+
+        let source = "a\n  b\n    c\nd\n\te\ng\n";
+        let config = crate::parse_config(source).unwrap();
+        assert_eq!(config.pretty_print(), source);
+        assert_eq!(
+            config.pretty_print_with("    ").unwrap(),
+            "a\n    b\n        c\nd\n    e\ng\n"
+        );
+
+        let mut built = Config::new("__top_level__", None);
+        built.entry("x").entry("y");
+        assert_eq!(built.pretty_print(), "x\n    y\n");
+    }
+
+    #[test]
+    fn retain() {
+        let source = std::fs::read_to_string("examples/config.nccl").unwrap();
+        let mut config = crate::parse_config(&source).unwrap();
+        config["server"].retain(|key, _| key == "port");
+        assert_eq!(config["server"].values().collect::<Vec<_>>(), vec!["port"]);
+        assert_eq!(config["server"]["port"].values().len(), 2);
+
+        config["server"]["port"].retain(|_, child| child.key() != "80");
+        assert_eq!(config["server"]["port"].value(), Some("443"));
     }
 }
@@ -0,0 +1,380 @@
+//! Serde deserialization for [`Config`]
+
+use crate::config::Config;
+use crate::NcclError;
+
+use alloc::borrow::Cow;
+use alloc::string::ToString;
+use core::fmt;
+use serde::de::{self, DeserializeSeed, IntoDeserializer, MapAccess, SeqAccess, Visitor};
+use serde::Deserialize;
+
+/// Deserialize a value from a config.
+///
+/// nccl has no types, so scalars are parsed from strings by the type being
+/// deserialized. A scalar is either a leaf, or a node with a single leaf
+/// child, so a struct field reads the value below its key. Sequences are
+/// the children of a node, and maps and structs are each child paired with
+/// its own children. Quoted keys have their escape sequences processed by
+/// [`Config::parse_quoted`].
+///
+/// ```
+/// #[derive(serde::Deserialize)]
+/// struct Server {
+///     root: String,
+///     port: Vec<u16>,
+/// }
+///
+/// let source = "root\n    /var/www\nport\n    80\n    443\n";
+/// let config = nccl::parse_config(&source).unwrap();
+/// let server: Server = nccl::from_config(&config).unwrap();
+/// assert_eq!(server.root, "/var/www");
+/// assert_eq!(server.port, vec![80, 443]);
+/// ```
+pub fn from_config<'de, T: Deserialize<'de>>(config: &'de Config) -> Result<T, NcclError> {
+    T::deserialize(Deserializer { node: config })
+}
+
+impl de::Error for NcclError {
+    fn custom<T: fmt::Display>(msg: T) -> Self {
+        NcclError::Deserialize {
+            message: msg.to_string(),
+        }
+    }
+}
+
+struct Deserializer<'de, 'a> {
+    node: &'de Config<'a>,
+}
+
+impl<'de> Deserializer<'de, '_> {
+    // a leaf is its own value, otherwise the node must have exactly one
+    fn scalar(&self) -> Result<Scalar<'de>, NcclError> {
+        let mut children = self.node.children();
+        let leaf = match (children.next(), children.next()) {
+            (None, _) => self.node,
+            (Some(child), None) if child.is_empty() => child,
+            _ => {
+                return Err(de::Error::custom(format_args!(
+                    "expected a single value for {:?} on line {}",
+                    self.node.key(),
+                    self.node.span().line,
+                )))
+            }
+        };
+        unescaped(leaf).map(Scalar)
+    }
+}
+
+fn unescaped<'de>(node: &'de Config<'_>) -> Result<Cow<'de, str>, NcclError> {
+    if node.quoted() {
+        node.parse_quoted().map(Cow::Owned)
+    } else {
+        Ok(Cow::Borrowed(node.key()))
+    }
+}
+
+macro_rules! forward_to_scalar {
+    ($($method:ident)*) => {$(
+        fn $method<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, NcclError> {
+            self.scalar()?.$method(visitor)
+        }
+    )*};
+}
+
+impl<'de> de::Deserializer<'de> for Deserializer<'de, '_> {
+    type Error = NcclError;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, NcclError> {
+        if self.node.is_empty() {
+            self.scalar()?.deserialize_any(visitor)
+        } else if self.node.children().all(Config::is_empty) {
+            self.deserialize_seq(visitor)
+        } else {
+            self.deserialize_map(visitor)
+        }
+    }
+
+    forward_to_scalar! {
+        deserialize_bool deserialize_char deserialize_str deserialize_string
+        deserialize_bytes deserialize_byte_buf deserialize_identifier
+        deserialize_i8 deserialize_i16 deserialize_i32 deserialize_i64 deserialize_i128
+        deserialize_u8 deserialize_u16 deserialize_u32 deserialize_u64 deserialize_u128
+        deserialize_f32 deserialize_f64
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, NcclError> {
+        visitor.visit_some(self)
+    }
+
+    fn deserialize_unit<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, NcclError> {
+        visitor.visit_unit()
+    }
+
+    fn deserialize_unit_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, NcclError> {
+        visitor.visit_unit()
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, NcclError> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_seq<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, NcclError> {
+        visitor.visit_seq(Children {
+            iter: self.node.children(),
+        })
+    }
+
+    fn deserialize_tuple<V: Visitor<'de>>(
+        self,
+        _len: usize,
+        visitor: V,
+    ) -> Result<V::Value, NcclError> {
+        self.deserialize_seq(visitor)
+    }
+
+    fn deserialize_tuple_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _len: usize,
+        visitor: V,
+    ) -> Result<V::Value, NcclError> {
+        self.deserialize_seq(visitor)
+    }
+
+    fn deserialize_map<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, NcclError> {
+        visitor.visit_map(Entries {
+            iter: self.node.children(),
+            value: None,
+        })
+    }
+
+    fn deserialize_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, NcclError> {
+        self.deserialize_map(visitor)
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        name: &'static str,
+        variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, NcclError> {
+        self.scalar()?.deserialize_enum(name, variants, visitor)
+    }
+
+    fn deserialize_ignored_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, NcclError> {
+        visitor.visit_unit()
+    }
+}
+
+// a single value, which is parsed into whatever type is asked for
+struct Scalar<'de>(Cow<'de, str>);
+
+macro_rules! parse_scalar {
+    ($($method:ident => $visit:ident,)*) => {$(
+        fn $method<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, NcclError> {
+            match self.0.parse() {
+                Ok(value) => visitor.$visit(value),
+                Err(_) => Err(de::Error::invalid_value(de::Unexpected::Str(&self.0), &visitor)),
+            }
+        }
+    )*};
+}
+
+impl<'de> de::Deserializer<'de> for Scalar<'de> {
+    type Error = NcclError;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, NcclError> {
+        match self.0 {
+            Cow::Borrowed(value) => visitor.visit_borrowed_str(value),
+            Cow::Owned(value) => visitor.visit_string(value),
+        }
+    }
+
+    parse_scalar! {
+        deserialize_bool => visit_bool,
+        deserialize_char => visit_char,
+        deserialize_i8 => visit_i8,
+        deserialize_i16 => visit_i16,
+        deserialize_i32 => visit_i32,
+        deserialize_i64 => visit_i64,
+        deserialize_i128 => visit_i128,
+        deserialize_u8 => visit_u8,
+        deserialize_u16 => visit_u16,
+        deserialize_u32 => visit_u32,
+        deserialize_u64 => visit_u64,
+        deserialize_u128 => visit_u128,
+        deserialize_f32 => visit_f32,
+        deserialize_f64 => visit_f64,
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, NcclError> {
+        visitor.visit_some(self)
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, NcclError> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, NcclError> {
+        visitor.visit_enum(self.0.into_deserializer())
+    }
+
+    serde::forward_to_deserialize_any! {
+        str string bytes byte_buf unit unit_struct seq tuple tuple_struct map
+        struct identifier ignored_any
+    }
+}
+
+struct Children<I> {
+    iter: I,
+}
+
+impl<'de, 'a: 'de, I: Iterator<Item = &'de Config<'a>>> SeqAccess<'de> for Children<I> {
+    type Error = NcclError;
+
+    fn next_element_seed<T: DeserializeSeed<'de>>(
+        &mut self,
+        seed: T,
+    ) -> Result<Option<T::Value>, NcclError> {
+        self.iter
+            .next()
+            .map(|node| seed.deserialize(Deserializer { node }))
+            .transpose()
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        self.iter.size_hint().1
+    }
+}
+
+struct Entries<'de, 'a, I> {
+    iter: I,
+    value: Option<&'de Config<'a>>,
+}
+
+impl<'de, 'a: 'de, I: Iterator<Item = &'de Config<'a>>> MapAccess<'de> for Entries<'de, 'a, I> {
+    type Error = NcclError;
+
+    fn next_key_seed<K: DeserializeSeed<'de>>(
+        &mut self,
+        seed: K,
+    ) -> Result<Option<K::Value>, NcclError> {
+        let Some(node) = self.iter.next() else {
+            return Ok(None);
+        };
+        self.value = Some(node);
+        seed.deserialize(Scalar(unescaped(node)?)).map(Some)
+    }
+
+    fn next_value_seed<V: DeserializeSeed<'de>>(&mut self, seed: V) -> Result<V::Value, NcclError> {
+        let node = self
+            .value
+            .take()
+            .expect("next_value_seed called before next_key_seed");
+        seed.deserialize(Deserializer { node })
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        self.iter.size_hint().1
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::NcclError;
+
+    use serde::Deserialize;
+    use std::collections::HashMap;
+
+    #[derive(Debug, PartialEq, Deserialize)]
+    struct Server {
+        domain: Vec<String>,
+        #[serde(rename = "port")]
+        ports: Vec<u16>,
+        root: String,
+    }
+
+    #[test]
+    fn from_config() {
+        let source = std::fs::read_to_string("examples/config.nccl").unwrap();
+        let config = crate::parse_config(&source).unwrap();
+
+        let server: Server = crate::from_config(&config["server"]).unwrap();
+        assert_eq!(
+            server,
+            Server {
+                domain: vec![String::from("example.com"), String::from("www.example.com")],
+                ports: vec![80, 443],
+                root: String::from("/var/www/html"),
+            }
+        );
+
+        let servers: HashMap<String, Server> = crate::from_config(&config).unwrap();
+        assert_eq!(servers["server"], server);
+    }
+
+    #[test]
+    fn from_config_scalars() {
+        #[derive(Debug, PartialEq, Deserialize)]
+        enum Mode {
+            Fast,
+            Slow,
+        }
+
+        #[derive(Debug, PartialEq, Deserialize)]
+        struct Options<'a> {
+            enabled: bool,
+            mode: Mode,
+            name: &'a str,
+            quoted: String,
+            missing: Option<u8>,
+        }
+
+        let source = "enabled\n    true\nmode\n    Slow\nname\n    nccl\nquoted\n    \"a\\tb\"\n";
+        let config = crate::parse_config(source).unwrap();
+        let options: Options = crate::from_config(&config).unwrap();
+        assert_eq!(
+            options,
+            Options {
+                enabled: true,
+                mode: Mode::Slow,
+                name: "nccl",
+                quoted: String::from("a\tb"),
+                missing: None,
+            }
+        );
+        assert_ne!(options.mode, Mode::Fast);
+
+        let config = crate::parse_config("port\n    eighty\n").unwrap();
+        assert!(matches!(
+            crate::from_config::<HashMap<String, u16>>(&config),
+            Err(NcclError::Deserialize { .. })
+        ));
+
+        let config = crate::parse_config("port\n    80\n    443\n").unwrap();
+        assert!(crate::from_config::<HashMap<String, u16>>(&config).is_err());
+    }
+}
@@ -89,6 +89,47 @@
 //! assert!(config["hello # this is part of the key!"].has_value("y'all"));
 //! ```
 //!
+//! Blank lines and lines with only a comment are skipped however they are
+//! indented, so they never end the children of a key.
+//!
+//! ```rust
+//! let source = "ports\n    80\n# not the end\n\n    443\n";
+//! let config = nccl::parse_config(&source).unwrap();
+//! assert_eq!(config["ports"].values().collect::<Vec<_>>(), vec!["80", "443"]);
+//! ```
+//!
+//! A value which starts with `#` can be written with a backslash in front of
+//! it, which is left out of the value.
+//!
+//! ```rust
+//! let source = r#"
+//! channels
+//!     \#rust
+//!     \#nccl # channel
+//! "#;
+//! let config = nccl::parse_config(&source).unwrap();
+//! assert!(config["channels"].has_value("#rust"));
+//! assert!(config["channels"].has_value("#nccl # channel"));
+//! ```
+//!
+//! A comment character later in a value can be escaped the same way, and the
+//! backslash is left out there too.
+//!
+//! ```rust
+//! let config = nccl::parse_config("topic\n    my \\# value\n").unwrap();
+//! assert_eq!(config["topic"].child().unwrap().key(), "my # value");
+//! assert!(config["topic"].has_value("my # value"));
+//! ```
+//!
+//! Likewise, a backslash before a leading space keeps the space, which would
+//! otherwise be taken as indentation.
+//!
+//! ```rust
+//! let source = "banner\n    \\   hello\n";
+//! let config = nccl::parse_config(&source).unwrap();
+//! assert_eq!(config["banner"].value(), Some("   hello"));
+//! ```
+//!
 //! Duplicate keys have their values merged.
 //!
 //! ```rust
@@ -116,8 +157,9 @@
 //! which would be the user's value.
 //!
 //! Values can have quotes if you want escape codes or multiple lines.
-//! Supported escape sequences are newlines, carriage returns, both quotes, and
-//! line breaks.
+//! Supported escape sequences are newlines, carriage returns, tabs, both quotes,
+//! unicode code points like `\u{1F600}`, the C control escapes `\0`, `\a`,
+//! `\b`, `\f`, and `\v`, and line breaks. See [`config::Config::parse_quoted`].
 //!
 //! ```rust
 //! let source = r#"
@@ -146,19 +188,41 @@
 //!         .collect::<Result<Vec<_>, _>>()
 //! );
 //! ```
+//!
+//! nccl only needs `alloc`, and works in `no_std` crates with default
+//! features disabled. The `std` feature, enabled by default, adds
+//! [`parse_config_from_reader`], [`OwnedConfig::interpolate_env`], and
+//! [`NcclError::Io`]. The `serde` feature implements `Serialize` for
+//! [`Config`], and adds `from_config` to deserialize one.
 
 #![allow(clippy::tabs_in_doc_comments)]
+#![cfg_attr(not(feature = "std"), no_std)]
+
+extern crate alloc;
 
 pub mod config;
+#[cfg(feature = "serde")]
+mod de;
 pub mod parser;
 pub mod scanner;
+#[cfg(feature = "serde")]
+mod ser;
+
+pub use config::{CommentMergePolicy, Config, MergePolicy, OwnedConfig};
+pub use scanner::Span;
+
+#[cfg(feature = "serde")]
+pub use de::from_config;
 
-pub use config::Config;
+use scanner::TokenKind;
 
-use scanner::{Span, TokenKind};
+use alloc::format;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use core::str::Utf8Error;
 
-use std::str::Utf8Error;
-use std::string::FromUtf8Error;
+#[cfg(feature = "std")]
+use std::io::Read;
 
 /// Parse a nccl configuration
 ///
@@ -185,13 +249,55 @@ use std::string::FromUtf8Error;
 /// // look ma, no types!
 /// assert_eq!(config["server"]["root"].value(), Some("/var/www/html"));
 /// ```
-pub fn parse_config(content: &str) -> Result<Config, NcclError> {
+pub fn parse_config(content: &str) -> Result<Config<'_>, NcclError> {
     let mut scanner = scanner::Scanner::new(content);
     parser::parse(&mut scanner)
 }
 
+/// Parse a nccl configuration from bytes
+///
+/// e.g.
+/// ```
+/// # use nccl::*;
+/// let config = parse_config_bytes(b"key\n    value\n").unwrap();
+/// assert_eq!(config["key"].value(), Some("value"));
+///
+/// assert!(matches!(
+///     parse_config_bytes(b"key\n    \xff\n"),
+///     Err(NcclError::Utf8 { .. })
+/// ));
+/// ```
+pub fn parse_config_bytes(bytes: &[u8]) -> Result<Config<'_>, NcclError> {
+    parse_config(core::str::from_utf8(bytes).map_err(|err| NcclError::utf8(bytes, 0, err))?)
+}
+
+/// Read and parse a nccl configuration
+///
+/// Since the source is read into a buffer owned by this function, the result
+/// is an [`OwnedConfig`].
+///
+/// e.g.
+/// ```
+/// # use nccl::*;
+/// let file = std::fs::File::open("examples/config.nccl").unwrap();
+/// let config = parse_config_from_reader(file).unwrap();
+/// assert_eq!(config["server"]["root"].value(), Some("/var/www/html"));
+/// ```
+#[cfg(feature = "std")]
+pub fn parse_config_from_reader<R: Read>(mut reader: R) -> Result<OwnedConfig, NcclError> {
+    let mut bytes = Vec::new();
+    reader.read_to_end(&mut bytes)?;
+    let content = String::from_utf8(bytes)
+        .map_err(|err| NcclError::utf8(err.as_bytes(), 0, err.utf8_error()))?;
+    let config = parse_config(&content)?.to_owned_config();
+    Ok(config)
+}
+
 /// Parse a new nccl configuration on top of another
 ///
+/// The base config is cloned first. Use [`parse_config_with_owned_base`] to
+/// avoid the clone when the base is no longer needed.
+///
 /// e.g.
 /// ```
 /// # use nccl::*;
@@ -233,11 +339,301 @@ pub fn parse_config_with<'a>(
     config: &Config<'a>,
     content: &'a str,
 ) -> Result<Config<'a>, NcclError> {
+    parse_config_with_owned_base(config.clone(), content)
+}
+
+/// Parse a new nccl configuration on top of another, consuming it
+///
+/// Like [`parse_config_with`], but without cloning the base. A key that the
+/// new configuration repeats is extended in place.
+///
+/// e.g.
+/// ```
+/// # use nccl::*;
+/// let user = std::fs::read_to_string("examples/user.nccl").unwrap();
+/// let user_config = parse_config(&user).unwrap();
+///
+/// let default = std::fs::read_to_string("examples/default.nccl").unwrap();
+/// let combined_config = parse_config_with_owned_base(user_config, &default).unwrap();
+/// assert_eq!(combined_config["beans"].values().collect::<Vec<_>>(), vec!["four", "none"]);
+/// assert_eq!(combined_config["frog"].value(), Some("yes"));
+/// ```
+pub fn parse_config_with_owned_base<'a>(
+    config: Config<'a>,
+    content: &'a str,
+) -> Result<Config<'a>, NcclError> {
+    let mut scanner = scanner::Scanner::new(content);
+    parser::parse_with(&mut scanner, config, ParseOptions::default())
+}
+
+/// Parse a new nccl configuration on top of an [`OwnedConfig`]
+///
+/// Like [`parse_config_with`], but the base config does not borrow from a
+/// source string, so each layer may be parsed from a separately owned source.
+///
+/// e.g.
+/// ```
+/// # use nccl::*;
+/// let user = std::fs::read_to_string("examples/user.nccl").unwrap();
+/// let user_config = parse_config(&user).unwrap().to_owned_config();
+/// drop(user);
+///
+/// let default = std::fs::read_to_string("examples/default.nccl").unwrap();
+/// let combined_config = parse_config_with_owned(user_config, &default).unwrap();
+/// assert_eq!(combined_config["beans"].value(), Some("four"));
+/// assert_eq!(combined_config["beans"].values().count(), 2);
+/// ```
+pub fn parse_config_with_owned(
+    mut config: OwnedConfig,
+    content: &str,
+) -> Result<OwnedConfig, NcclError> {
+    config.merge(parse_config(content)?.to_owned_config());
+    Ok(config)
+}
+
+/// Parse a nccl configuration with non-default options
+///
+/// e.g.
+/// ```
+/// # use nccl::*;
+/// let source = "color\n    #ff0000 ; red\n; a comment\n";
+/// let options = ParseOptions {
+///     comment_char: ';',
+///     ..Default::default()
+/// };
+/// let config = parse_config_with_options(source, options).unwrap();
+/// assert_eq!(config["color"].value(), Some("#ff0000 ; red"));
+/// ```
+///
+/// Returns [`NcclError::InvalidCommentChar`],
+/// [`NcclError::InvalidIndentChar`], or
+/// [`NcclError::InvalidInlineSeparator`] if the options name a character
+/// which can't be used, and [`NcclError::InvalidTabWidth`] if
+/// [`ParseOptions::tab_width`] is zero.
+pub fn parse_config_with_options(
+    content: &str,
+    options: ParseOptions,
+) -> Result<Config<'_>, NcclError> {
+    let mut scanner = scanner::Scanner::with_options(content, &options)?;
+    parser::parse_with(
+        &mut scanner,
+        Config::new(parser::TOP_LEVEL_KEY, None),
+        options,
+    )
+}
+
+/// Parse several nccl configurations separated by a marker line
+///
+/// A line consisting of exactly `separator`, starting at the beginning of the
+/// line, ends one document and begins the next. Each document is parsed
+/// independently. Lines and offsets in spans and errors are counted from the
+/// start of `content`.
+///
+/// Returns [`NcclError::InvalidDocumentSeparator`] if `separator` is empty or
+/// spans more than one line.
+///
+/// e.g.
+/// ```
+/// # use nccl::*;
+/// let source = "name\n    first\n---\nname\n    second\n";
+/// let documents = parse_documents(source, "---").unwrap();
+/// assert_eq!(documents.len(), 2);
+/// assert_eq!(documents[1]["name"].value(), Some("second"));
+/// assert_eq!(documents[1]["name"].span().line, 4);
+/// assert_eq!(documents[1]["name"].span().offset, 19);
+/// ```
+pub fn parse_documents<'a>(
+    content: &'a str,
+    separator: &str,
+) -> Result<Vec<Config<'a>>, NcclError> {
+    if separator.is_empty() || separator.contains(['\n', '\r']) {
+        return Err(NcclError::InvalidDocumentSeparator {
+            separator: String::from(separator),
+        });
+    }
+
+    let mut documents = Vec::new();
+    let mut start = 0;
+    let mut start_line = 1;
+    let mut offset = 0;
+
+    for (i, line) in content.split_inclusive('\n').enumerate() {
+        if line.trim_end_matches(['\n', '\r']) == separator {
+            documents.push(parse_document(&content[start..offset], start, start_line)?);
+            start = offset + line.len();
+            start_line = i + 2;
+        }
+        offset += line.len();
+    }
+
+    documents.push(parse_document(&content[start..], start, start_line)?);
+    Ok(documents)
+}
+
+fn parse_document(content: &str, offset: usize, line: usize) -> Result<Config<'_>, NcclError> {
     let mut scanner = scanner::Scanner::new(content);
-    parser::parse_with(&mut scanner, config)
+    scanner.base_offset = offset;
+    scanner.line = line;
+    parser::parse(&mut scanner)
+}
+
+/// Parse a nccl configuration, also reporting suspicious constructs
+///
+/// The warnings are for input the grammar accepts but which is likely a
+/// mistake, such as an unquoted value with trailing whitespace, or a key
+/// indented by more than one level relative to its parent.
+///
+/// e.g.
+/// ```
+/// # use nccl::*;
+/// let source = "a\n    1\nb\n        2\n";
+/// let (config, warnings) = parse_config_verbose(source).unwrap();
+/// assert_eq!(config["b"].value(), Some("2"));
+/// assert_eq!(warnings.len(), 1);
+/// assert_eq!(warnings[0].span.line, 4);
+/// ```
+pub fn parse_config_verbose(content: &str) -> Result<(Config<'_>, Vec<Warning>), NcclError> {
+    let config = parse_config(content)?;
+
+    let mut warnings = Vec::new();
+    let mut jumps = Vec::new();
+    for child in config.children() {
+        collect_warnings(content, child, "", &mut jumps, &mut warnings);
+    }
+
+    // the narrowest indentation in the file is taken to be a single level
+    if let Some(&unit) = jumps.iter().map(|(_, jump)| jump).min() {
+        for (span, jump) in jumps {
+            if jump > unit && jump % unit == 0 {
+                warnings.push(Warning {
+                    span,
+                    message: format!(
+                        "indented by {} spaces, which is {} levels of {}",
+                        jump,
+                        jump / unit,
+                        unit
+                    ),
+                });
+            }
+        }
+    }
+
+    warnings.sort_by_key(|warning| warning.span.offset);
+    Ok((config, warnings))
+}
+
+fn collect_warnings(
+    content: &str,
+    node: &Config<'_>,
+    parent_indent: &str,
+    jumps: &mut Vec<(Span, usize)>,
+    warnings: &mut Vec<Warning>,
+) {
+    let line_start = content[..node.span.offset]
+        .rfind('\n')
+        .map(|i| i + 1)
+        .unwrap_or(0);
+    let line = &content[line_start..];
+    let indent = &line[..line.len() - line.trim_start_matches([' ', '\t']).len()];
+
+    if !parent_indent.contains('\t') && !indent.contains('\t') && indent.len() > parent_indent.len()
+    {
+        jumps.push((node.span, indent.len() - parent_indent.len()));
+    }
+
+    if !node.quoted() && node.key.ends_with([' ', '\t']) {
+        warnings.push(Warning {
+            span: node.span,
+            message: String::from("unquoted value has trailing whitespace"),
+        });
+    }
+
+    for child in node.children() {
+        collect_warnings(content, child, indent, jumps, warnings);
+    }
+}
+
+/// A suspicious construct found by [`parse_config_verbose`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Warning {
+    /// The location of the construct.
+    pub span: Span,
+    /// A description of what looks wrong.
+    pub message: String,
+}
+
+/// Options for [`parse_config_with_options`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ParseOptions {
+    /// The character that starts a comment line. Must be ASCII punctuation
+    /// other than a quote or backslash.
+    pub comment_char: char,
+    /// What to do when a key appears twice under the same parent.
+    pub duplicate_keys: DuplicatePolicy,
+    /// The deepest level of indentation allowed, or `None` for no limit.
+    /// Defaults to `Some(256)`.
+    ///
+    /// Parsing, [`Config::walk`], printing, and dropping don't recurse, so a
+    /// deeper limit is safe for them. Cloning and comparing with `==` still
+    /// recurse once per level, and may overflow the stack for configs tens of
+    /// thousands of levels deep.
+    pub max_depth: Option<usize>,
+    /// The longest value allowed, in bytes. Defaults to unlimited.
+    pub max_value_len: Option<usize>,
+    /// The most keys allowed in total, not counting repeated keys which are
+    /// merged. Defaults to unlimited.
+    pub max_nodes: Option<usize>,
+    /// The number of spaces a tab is equivalent to. If set, lines indented
+    /// with tabs and lines indented with spaces may be mixed freely, otherwise
+    /// each top-level key must use only one kind of indentation. A single
+    /// line may never use both. Must not be zero. Defaults to `None`.
+    pub tab_width: Option<usize>,
+    /// Whether to keep comment lines, attaching them to the key that follows.
+    /// See [`Config::comment`]. Defaults to `false`.
+    pub keep_comments: bool,
+    /// A character separating a top-level key from a value on the same line,
+    /// as in `name: nccl`. Must not be whitespace, a control character, a
+    /// quote, a backslash, or the comment character. Defaults to `None`, where
+    /// the whole line is the key.
+    pub inline_separator: Option<char>,
+    /// Whether to also allow `//` line comments and `/* */` block comments.
+    /// Like `#`, these must begin on their own line or after a quoted value.
+    /// Defaults to `false`.
+    pub c_comments: bool,
+    /// Another whitespace character to count as a space in indentation, either
+    /// a vertical tab `'\x0B'` or a form feed `'\x0C'`. Defaults to `None`.
+    pub indent_char: Option<char>,
+}
+
+impl Default for ParseOptions {
+    fn default() -> Self {
+        ParseOptions {
+            comment_char: '#',
+            duplicate_keys: DuplicatePolicy::default(),
+            max_depth: Some(256),
+            max_value_len: None,
+            max_nodes: None,
+            tab_width: None,
+            keep_comments: false,
+            inline_separator: None,
+            c_comments: false,
+            indent_char: None,
+        }
+    }
+}
+
+/// How to handle a key which appears more than once under the same parent
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum DuplicatePolicy {
+    /// Merge the values of both keys. See [`parse_config_with`].
+    #[default]
+    Merge,
+    /// Return [`NcclError::DuplicateKey`].
+    Error,
 }
 
 #[derive(Debug, PartialEq)]
+#[non_exhaustive]
 /// Errors that may occur while parsing
 pub enum NcclError {
     /// An unexpected token was encountered.
@@ -249,11 +645,70 @@ pub enum NcclError {
         /// The kind of token we got.
         got: TokenKind,
     },
+    /// A key was repeated while using [`DuplicatePolicy::Error`].
+    DuplicateKey {
+        /// The location of the repeated key.
+        span: Span,
+    },
+    /// Tabs and spaces were mixed within a single top-level key.
+    InconsistentIndentation {
+        /// The location of the indentation.
+        span: Span,
+        /// The kind of indentation the key was using.
+        expected: TokenKind,
+        /// The kind of indentation we got.
+        got: TokenKind,
+    },
+    /// A key was indented further than one level below its parent.
+    UnexpectedIndent {
+        /// The location of the indentation.
+        span: Span,
+        /// The indentation of the next level.
+        expected: TokenKind,
+        /// The indentation we got.
+        got: TokenKind,
+    },
+    /// A line was indented with both tabs and spaces.
+    MixedIndentation {
+        /// The location of the indentation.
+        span: Span,
+    },
+    /// Keys were nested deeper than [`ParseOptions::max_depth`].
+    MaxDepthExceeded {
+        /// The location of the indentation.
+        span: Span,
+        /// The maximum depth.
+        limit: usize,
+    },
+    /// A value was longer than [`ParseOptions::max_value_len`].
+    ValueTooLong {
+        /// The location of the value.
+        span: Span,
+        /// The maximum length.
+        limit: usize,
+    },
+    /// There were more keys than [`ParseOptions::max_nodes`].
+    TooManyNodes {
+        /// The location of the key over the limit.
+        span: Span,
+        /// The maximum number of keys.
+        limit: usize,
+    },
     /// The string was not terminated before the end of the file.
     UnterminatedString {
         /// The line the string starts on.
         start: usize,
     },
+    /// A block comment was not terminated before the end of the file.
+    UnterminatedComment {
+        /// The line the comment starts on.
+        start: usize,
+    },
+    /// A quoted value ended with a backslash that escapes nothing.
+    DanglingEscape {
+        /// The line of the backslash.
+        line: usize,
+    },
     /// There were non-comment characters after a quoted string.
     TrailingCharacters {
         /// The line the string ends on.
@@ -263,25 +718,221 @@ pub enum NcclError {
     ScanUnknownEscape {
         /// The line of the code.
         line: usize,
-        /// The column of the code.
+        /// The column of the code, counted in characters from 0.
         column: usize,
         /// The code itself.
         escape: char,
     },
+    /// A unicode escape was malformed or named an invalid code point.
+    InvalidUnicodeEscape {
+        /// The line of the escape.
+        line: usize,
+    },
     /// The escape literal in the key was unknown. See [`crate::config::Config::parse_quoted`].
     ParseUnknownEscape {
         /// The escape code.
         escape: char,
+        /// The line of the code.
+        line: usize,
+    },
+    /// A `${path}` reference named a node that does not exist or has no value,
+    /// or an environment variable that is not set. See
+    /// [`crate::config::Config::resolve`] and
+    /// [`crate::config::OwnedConfig::interpolate_env`].
+    UnresolvedReference {
+        /// The path that was referenced.
+        reference: String,
+        /// The line of the value containing the reference.
+        line: usize,
+    },
+    /// A `${path}` reference eventually refers back to itself.
+    ReferenceCycle {
+        /// The path that was referenced.
+        reference: String,
+        /// The line of the value containing the reference.
+        line: usize,
+    },
+    /// [`ParseOptions::comment_char`] was not ASCII punctuation, or was a quote
+    /// or backslash.
+    InvalidCommentChar {
+        /// The comment character.
+        comment_char: char,
+    },
+    /// [`ParseOptions::indent_char`] was not a space, vertical tab, or form
+    /// feed.
+    InvalidIndentChar {
+        /// The indentation character.
+        indent_char: char,
+    },
+    /// The separator passed to [`parse_documents`] was empty or spanned more
+    /// than one line.
+    InvalidDocumentSeparator {
+        /// The separator.
+        separator: String,
+    },
+    /// [`ParseOptions::tab_width`] was zero.
+    InvalidTabWidth {
+        /// The tab width.
+        tab_width: usize,
+    },
+    /// Tabs converted to spaces with [`ParseOptions::tab_width`] were too wide
+    /// to count.
+    IndentOverflow {
+        /// The location of the indentation.
+        span: Span,
+    },
+    /// [`ParseOptions::inline_separator`] was whitespace, a control character,
+    /// a quote, a backslash, or the comment character.
+    InvalidInlineSeparator {
+        /// The separator.
+        separator: char,
+    },
+    /// The indentation passed to [`Config::pretty_print_with`] was empty, or
+    /// mixed spaces with tabs or other characters.
+    InvalidPrintIndent {
+        /// The indentation.
+        indent: String,
+    },
+    /// The source could not be read.
+    #[cfg(feature = "std")]
+    Io {
+        /// The kind of error.
+        kind: std::io::ErrorKind,
+        /// The error message.
+        message: String,
+    },
+    /// A config could not be deserialized. See [`from_config`].
+    #[cfg(feature = "serde")]
+    Deserialize {
+        /// The error message.
+        message: String,
     },
-    /// A utf-8 string could not be constructed.
+    /// The source contained invalid utf-8.
     Utf8 {
         /// The error.
         err: Utf8Error,
+        /// The line the invalid sequence began on.
+        line: usize,
+        /// The column the invalid sequence began at, counted in characters.
+        column: usize,
     },
 }
 
-impl std::fmt::Display for NcclError {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+impl NcclError {
+    /// The line the error occurred on, if it has one.
+    ///
+    /// ```
+    /// let err = nccl::parse_config("a\n    \"b\n").unwrap_err();
+    /// assert_eq!(err.line(), Some(2));
+    /// ```
+    pub fn line(&self) -> Option<usize> {
+        match self {
+            NcclError::UnexpectedToken { span, .. }
+            | NcclError::DuplicateKey { span }
+            | NcclError::InconsistentIndentation { span, .. }
+            | NcclError::UnexpectedIndent { span, .. }
+            | NcclError::MixedIndentation { span }
+            | NcclError::MaxDepthExceeded { span, .. }
+            | NcclError::ValueTooLong { span, .. }
+            | NcclError::TooManyNodes { span, .. }
+            | NcclError::IndentOverflow { span } => Some(span.line),
+            NcclError::UnterminatedString { start: line }
+            | NcclError::UnterminatedComment { start: line }
+            | NcclError::DanglingEscape { line }
+            | NcclError::TrailingCharacters { line }
+            | NcclError::ScanUnknownEscape { line, .. }
+            | NcclError::InvalidUnicodeEscape { line }
+            | NcclError::ParseUnknownEscape { line, .. }
+            | NcclError::UnresolvedReference { line, .. }
+            | NcclError::ReferenceCycle { line, .. }
+            | NcclError::Utf8 { line, .. } => Some(*line),
+            NcclError::InvalidCommentChar { .. }
+            | NcclError::InvalidIndentChar { .. }
+            | NcclError::InvalidTabWidth { .. }
+            | NcclError::InvalidDocumentSeparator { .. }
+            | NcclError::InvalidInlineSeparator { .. }
+            | NcclError::InvalidPrintIndent { .. } => None,
+            #[cfg(feature = "std")]
+            NcclError::Io { .. } => None,
+            #[cfg(feature = "serde")]
+            NcclError::Deserialize { .. } => None,
+        }
+    }
+
+    // the position reported by a utf-8 error is relative to the slice that
+    // was checked, so find where it starts in the whole source
+    pub(crate) fn utf8(source: &[u8], start: usize, err: Utf8Error) -> NcclError {
+        let at = start + err.valid_up_to();
+        let line_start = source[..at]
+            .iter()
+            .rposition(|&byte| byte == b'\n')
+            .map_or(0, |i| i + 1);
+
+        NcclError::Utf8 {
+            err,
+            line: 1 + source[..at].iter().filter(|&&byte| byte == b'\n').count(),
+            column: source[line_start..at]
+                .iter()
+                .filter(|&&byte| !scanner::is_continuation(byte))
+                .count(),
+        }
+    }
+
+    /// Render the error along with the line of the source it occurred on,
+    /// with a caret under the location of the error if it is known.
+    ///
+    /// ```
+    /// let source = "key\n    \"bad \\q\"\n";
+    /// let err = nccl::parse_config(source).unwrap_err();
+    /// assert_eq!(
+    ///     err.with_source(source),
+    ///     "unknown escape 'q' at 2:10\n2 |     \"bad \\q\"\n  |           ^\n"
+    /// );
+    /// ```
+    pub fn with_source(&self, source: &str) -> String {
+        let mut rendered = format!("{}\n", self);
+
+        let Some(line) = self.line() else {
+            return rendered;
+        };
+        let Some(text) = source.lines().nth(line.wrapping_sub(1)) else {
+            return rendered;
+        };
+
+        let column = match self {
+            NcclError::UnexpectedToken { span, .. }
+            | NcclError::DuplicateKey { span }
+            | NcclError::InconsistentIndentation { span, .. }
+            | NcclError::UnexpectedIndent { span, .. }
+            | NcclError::MixedIndentation { span }
+            | NcclError::MaxDepthExceeded { span, .. }
+            | NcclError::ValueTooLong { span, .. }
+            | NcclError::TooManyNodes { span, .. }
+            | NcclError::IndentOverflow { span } => source
+                .get(..span.offset)
+                .map(|before| before.rsplit('\n').next().unwrap_or("").chars().count()),
+            NcclError::ScanUnknownEscape { column, .. } | NcclError::Utf8 { column, .. } => {
+                Some(*column)
+            }
+            _ => None,
+        };
+
+        let gutter = line.to_string();
+        rendered.push_str(&format!("{} | {}\n", gutter, text));
+        if let Some(column) = column {
+            rendered.push_str(&format!(
+                "{} | {}^\n",
+                " ".repeat(gutter.len()),
+                " ".repeat(column)
+            ));
+        }
+
+        rendered
+    }
+}
+
+impl core::fmt::Display for NcclError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
         match self {
             NcclError::UnexpectedToken {
                 span,
@@ -292,9 +943,56 @@ impl std::fmt::Display for NcclError {
                 "expected {:?}, got {:?} at {}:{}",
                 expected, got, span.line, span.column,
             ),
+            NcclError::DuplicateKey { span } => {
+                write!(f, "duplicate key at {}:{}", span.line, span.column)
+            }
+            NcclError::InconsistentIndentation {
+                span,
+                expected,
+                got,
+            } => write!(
+                f,
+                "inconsistent indentation, expected {:?}, got {:?} at {}:{}",
+                expected, got, span.line, span.column,
+            ),
+            NcclError::UnexpectedIndent {
+                span,
+                expected,
+                got,
+            } => write!(
+                f,
+                "unexpected indentation, expected {:?}, got {:?} at {}:{}",
+                expected, got, span.line, span.column,
+            ),
+            NcclError::MixedIndentation { span } => write!(
+                f,
+                "tabs and spaces mixed in indentation at {}:{}",
+                span.line, span.column,
+            ),
+            NcclError::MaxDepthExceeded { span, limit } => write!(
+                f,
+                "nesting deeper than {} levels at {}:{}",
+                limit, span.line, span.column,
+            ),
+            NcclError::ValueTooLong { span, limit } => write!(
+                f,
+                "value longer than {} bytes at {}:{}",
+                limit, span.line, span.column,
+            ),
+            NcclError::TooManyNodes { span, limit } => write!(
+                f,
+                "more than {} keys at {}:{}",
+                limit, span.line, span.column,
+            ),
             NcclError::UnterminatedString { start } => {
                 write!(f, "unterminated string starting on line {}", start)
             }
+            NcclError::UnterminatedComment { start } => {
+                write!(f, "unterminated comment starting on line {}", start)
+            }
+            NcclError::DanglingEscape { line } => {
+                write!(f, "backslash at end of string on line {}", line)
+            }
             NcclError::TrailingCharacters { line } => {
                 write!(f, "characters after string on line {}", line)
             }
@@ -303,22 +1001,66 @@ impl std::fmt::Display for NcclError {
                 line,
                 column,
             } => write!(f, "unknown escape {:?} at {}:{}", escape, line, column),
-            NcclError::ParseUnknownEscape { escape } => write!(f, "unknown escape {:?}", escape),
-            NcclError::Utf8 { err } => write!(f, "{}", err),
+            NcclError::InvalidUnicodeEscape { line } => {
+                write!(f, "invalid unicode escape on line {}", line)
+            }
+            NcclError::ParseUnknownEscape { escape, line } => {
+                write!(f, "unknown escape {:?} on line {}", escape, line)
+            }
+            NcclError::UnresolvedReference { reference, line } => {
+                write!(f, "unresolved reference {:?} on line {}", reference, line)
+            }
+            NcclError::ReferenceCycle { reference, line } => {
+                write!(
+                    f,
+                    "reference cycle through {:?} on line {}",
+                    reference, line
+                )
+            }
+            NcclError::InvalidCommentChar { comment_char } => {
+                write!(f, "{:?} can't be used as a comment character", comment_char)
+            }
+            NcclError::InvalidIndentChar { indent_char } => write!(
+                f,
+                "{:?} can't be used as an indentation character",
+                indent_char
+            ),
+            NcclError::InvalidDocumentSeparator { separator } => {
+                write!(f, "{:?} can't be used as a document separator", separator)
+            }
+            NcclError::InvalidTabWidth { tab_width } => {
+                write!(f, "tab width {} is not allowed", tab_width)
+            }
+            NcclError::IndentOverflow { span } => write!(
+                f,
+                "indentation too wide to count at {}:{}",
+                span.line, span.column,
+            ),
+            NcclError::InvalidInlineSeparator { separator } => {
+                write!(f, "{:?} can't be used as an inline separator", separator)
+            }
+            NcclError::InvalidPrintIndent { indent } => {
+                write!(f, "{:?} can't be used as indentation", indent)
+            }
+            #[cfg(feature = "std")]
+            NcclError::Io { message, .. } => write!(f, "{}", message),
+            #[cfg(feature = "serde")]
+            NcclError::Deserialize { message } => write!(f, "{}", message),
+            NcclError::Utf8 { line, column, .. } => {
+                write!(f, "invalid utf-8 at {}:{}", line, column)
+            }
         }
     }
 }
 
-impl From<Utf8Error> for NcclError {
-    fn from(err: Utf8Error) -> Self {
-        NcclError::Utf8 { err }
-    }
-}
+impl core::error::Error for NcclError {}
 
-impl From<FromUtf8Error> for NcclError {
-    fn from(err: FromUtf8Error) -> Self {
-        NcclError::Utf8 {
-            err: err.utf8_error(),
+#[cfg(feature = "std")]
+impl From<std::io::Error> for NcclError {
+    fn from(err: std::io::Error) -> Self {
+        NcclError::Io {
+            kind: err.kind(),
+            message: err.to_string(),
         }
     }
 }
@@ -373,6 +1115,10 @@ mod test {
 
         let content2 = read_to_string("examples/duplicates2.nccl").unwrap();
         let config2 = parse_config_with(&config1, &content2).unwrap();
+        assert_eq!(
+            config2,
+            parse_config_with_owned_base(config1, &content2).unwrap()
+        );
 
         assert_eq!(2, config2["something"].values().collect::<Vec<_>>().len());
     }
@@ -423,6 +1169,43 @@ does this work?
         assert!(config["does this work?"].has_value("is this a child?"));
     }
 
+    #[test]
+    fn comment_indentation() {
+        let sources = [
+            "a\n    b\n        x\n  # c\n        y\n    d\n",
+            "a\n    b\n        x\n\t# c\n        y\n    d\n",
+            "a\n    b\n        x\n  \n        y\n    d\n",
+            "a\n\tb\n\t\tx\n\t# c\n\t\ty\n\td\n",
+            "a\n\tb\n\t\tx\n  # c\n\t\ty\n\td\n",
+            "a\r\n    b\r\n        x\r\n# c\r\n        y\r\n    d\r\n",
+        ];
+
+        for source in sources {
+            for keep_comments in [false, true] {
+                let options = ParseOptions {
+                    keep_comments,
+                    ..Default::default()
+                };
+                let config = parse_config_with_options(source, options).unwrap();
+                assert_eq!(config["a"].values().collect::<Vec<_>>(), vec!["b", "d"]);
+                assert_eq!(
+                    config["a"]["b"].values().collect::<Vec<_>>(),
+                    vec!["x", "y"],
+                    "{:?}",
+                    source
+                );
+            }
+        }
+
+        let options = ParseOptions {
+            c_comments: true,
+            ..Default::default()
+        };
+        let source = "a\n    b\n /* c\n       d */\n    e\n";
+        let config = parse_config_with_options(source, options).unwrap();
+        assert_eq!(config["a"].values().collect::<Vec<_>>(), vec!["b", "e"]);
+    }
+
     #[test]
     fn all_of_em() {
         let source = read_to_string("examples/all-of-em.nccl").unwrap();
@@ -515,4 +1298,335 @@ does this work?
             }
         }
     }
+
+    #[test]
+    fn comment_char() {
+        let source = r##"; comment
+colors
+    ; another comment
+    #ff0000
+    #00ff00 # not a comment
+    "#0000ff" ; trailing comment
+"##;
+        let options = ParseOptions {
+            comment_char: ';',
+            ..Default::default()
+        };
+        let config = parse_config_with_options(source, options).unwrap();
+        assert_eq!(
+            config["colors"].values().collect::<Vec<_>>(),
+            vec!["#ff0000", "#00ff00 # not a comment", "#0000ff"]
+        );
+
+        for comment_char in [
+            '§', ' ', '\t', '\n', '\r', '"', '\'', '\\', '\0', '\x7f', 'a', '7',
+        ] {
+            let options = ParseOptions {
+                comment_char,
+                ..Default::default()
+            };
+            assert_eq!(
+                parse_config_with_options(source, options),
+                Err(NcclError::InvalidCommentChar { comment_char })
+            );
+        }
+
+        let config = parse_config_with_options("; a\n# b\n", ParseOptions::default()).unwrap();
+        assert_eq!(config.values().collect::<Vec<_>>(), vec!["; a"]);
+    }
+
+    #[test]
+    fn strict_duplicates() {
+        let options = ParseOptions {
+            duplicate_keys: DuplicatePolicy::Error,
+            ..Default::default()
+        };
+
+        let source = read_to_string("examples/duplicates.nccl").unwrap();
+        match parse_config_with_options(&source, options) {
+            Err(NcclError::DuplicateKey { span }) => assert_eq!(span.line, 5),
+            result => panic!("expected duplicate key error, got {:?}", result),
+        }
+
+        let source = read_to_string("examples/config.nccl").unwrap();
+        assert_eq!(
+            parse_config_with_options(&source, options).unwrap(),
+            parse_config(&source).unwrap()
+        );
+    }
+
+    #[test]
+    fn bytes() {
+        let source = std::fs::read("examples/config.nccl").unwrap();
+        let config = parse_config_bytes(&source).unwrap();
+        assert_eq!(config["server"]["root"].value(), Some("/var/www/html"));
+
+        let err = parse_config_bytes(b"server\n    \xc3\x28\n").unwrap_err();
+        assert!(matches!(
+            err,
+            NcclError::Utf8 { err, line: 2, column: 4 } if err.valid_up_to() == 11
+        ));
+        assert_eq!(err.to_string(), "invalid utf-8 at 2:4");
+
+        let source = ["ключ\n    зна".as_bytes(), b"\xd0\n"].concat();
+        let err = parse_config_bytes(&source).unwrap_err();
+        assert!(matches!(
+            err,
+            NcclError::Utf8 {
+                line: 2,
+                column: 7,
+                ..
+            }
+        ));
+    }
+
+    #[test]
+    fn reader() {
+        let file = std::fs::File::open("examples/config.nccl").unwrap();
+        let config = parse_config_from_reader(file).unwrap();
+        assert_eq!(config["server"]["root"].value(), Some("/var/www/html"));
+        assert_eq!(
+            config["server"]["port"].values().collect::<Vec<_>>(),
+            vec!["80", "443"]
+        );
+
+        let bytes: &[u8] = b"key\n    \xff\n";
+        assert!(matches!(
+            parse_config_from_reader(bytes),
+            Err(NcclError::Utf8 { .. })
+        ));
+
+        struct Broken;
+        impl Read for Broken {
+            fn read(&mut self, _: &mut [u8]) -> std::io::Result<usize> {
+                Err(std::io::Error::other("broken"))
+            }
+        }
+        assert_eq!(
+            parse_config_from_reader(Broken),
+            Err(NcclError::Io {
+                kind: std::io::ErrorKind::Other,
+                message: String::from("broken"),
+            })
+        );
+    }
+
+    #[test]
+    fn max_value_len() {
+        let options = ParseOptions {
+            max_value_len: Some(1024),
+            ..Default::default()
+        };
+
+        let source = format!("key\n    {}\n", "a".repeat(1024 * 1024));
+        match parse_config_with_options(&source, options) {
+            Err(NcclError::ValueTooLong { span, limit }) => {
+                assert_eq!(limit, 1024);
+                assert_eq!(span.line, 2);
+                assert_eq!(span.len, 1024 * 1024);
+            }
+            result => panic!("expected value too long, got {:?}", result),
+        }
+
+        let source = format!("key\n    '{}'\n", "a".repeat(1025));
+        assert!(matches!(
+            parse_config_with_options(&source, options),
+            Err(NcclError::ValueTooLong { .. })
+        ));
+
+        let source = format!("key\n    {}\n", "a".repeat(1024));
+        assert!(parse_config_with_options(&source, options).is_ok());
+        assert!(parse_config(&format!("key\n    {}\n", "a".repeat(1024 * 1024))).is_ok());
+    }
+
+    #[test]
+    fn layer_owned() {
+        fn layer(base: OwnedConfig, source: String) -> OwnedConfig {
+            parse_config_with_owned(base, &source).unwrap()
+        }
+
+        let base = parse_config("a\n    1\nb\n    2\n")
+            .unwrap()
+            .to_owned_config();
+        let config = layer(base, String::from("a\n    3\nc\n    4\n"));
+        let config = layer(config, String::from("b\n    5\nd\n    6\n"));
+
+        assert_eq!(
+            config
+                .children()
+                .map(|child| child.key())
+                .collect::<Vec<_>>(),
+            vec!["a", "b", "c", "d"]
+        );
+        assert_eq!(config["a"].values().collect::<Vec<_>>(), vec!["1", "3"]);
+        assert_eq!(config["b"].values().collect::<Vec<_>>(), vec!["2", "5"]);
+        assert_eq!(config["d"].value(), Some("6"));
+    }
+
+    #[test]
+    fn verbose_warnings() {
+        let source = "a\n    1\n    2 \nb\n        3\n                4\n";
+        let (config, warnings) = parse_config_verbose(source).unwrap();
+        assert_eq!(config["b"]["3"].value(), Some("4"));
+        assert_eq!(
+            warnings
+                .iter()
+                .map(|warning| (warning.span.line, warning.message.as_str()))
+                .collect::<Vec<_>>(),
+            vec![
+                (3, "unquoted value has trailing whitespace"),
+                (5, "indented by 8 spaces, which is 2 levels of 4"),
+                (6, "indented by 8 spaces, which is 2 levels of 4"),
+            ]
+        );
+
+        let content = read_to_string("examples/config.nccl").unwrap();
+        assert_eq!(parse_config_verbose(&content).unwrap().1, vec![]);
+    }
+
+    #[test]
+    fn error_line() {
+        let span = Span {
+            line: 1,
+            ..Span::default()
+        };
+        let errors = [
+            NcclError::UnexpectedToken {
+                span,
+                expected: TokenKind::Value,
+                got: TokenKind::Eof,
+            },
+            NcclError::DuplicateKey { span },
+            NcclError::InconsistentIndentation {
+                span,
+                expected: TokenKind::Tabs(1),
+                got: TokenKind::Spaces(4),
+            },
+            NcclError::UnexpectedIndent {
+                span,
+                expected: TokenKind::Spaces(4),
+                got: TokenKind::Spaces(8),
+            },
+            NcclError::MixedIndentation { span },
+            NcclError::MaxDepthExceeded { span, limit: 1 },
+            NcclError::ValueTooLong { span, limit: 1 },
+            NcclError::TooManyNodes { span, limit: 1 },
+            NcclError::IndentOverflow { span },
+            NcclError::UnterminatedString { start: 1 },
+            NcclError::UnterminatedComment { start: 1 },
+            NcclError::DanglingEscape { line: 1 },
+            NcclError::TrailingCharacters { line: 1 },
+            NcclError::ScanUnknownEscape {
+                line: 1,
+                column: 2,
+                escape: 'q',
+            },
+            NcclError::InvalidUnicodeEscape { line: 1 },
+            NcclError::ParseUnknownEscape {
+                escape: 'q',
+                line: 1,
+            },
+            NcclError::UnresolvedReference {
+                reference: String::from("a"),
+                line: 1,
+            },
+            NcclError::ReferenceCycle {
+                reference: String::from("a"),
+                line: 1,
+            },
+        ];
+        for err in errors {
+            assert_eq!(err.line(), Some(1), "{:?}", err);
+        }
+
+        let io = std::io::Error::other("oops");
+        assert_eq!(NcclError::from(io).line(), None);
+        assert_eq!(
+            NcclError::InvalidCommentChar { comment_char: 'é' }.line(),
+            None
+        );
+        assert_eq!(
+            NcclError::InvalidIndentChar { indent_char: '\t' }.line(),
+            None
+        );
+        assert_eq!(
+            NcclError::InvalidInlineSeparator { separator: ' ' }.line(),
+            None
+        );
+        assert_eq!(NcclError::InvalidTabWidth { tab_width: 0 }.line(), None);
+        assert_eq!(
+            NcclError::InvalidPrintIndent {
+                indent: String::new()
+            }
+            .line(),
+            None
+        );
+        assert_eq!(
+            NcclError::InvalidDocumentSeparator {
+                separator: String::new()
+            }
+            .line(),
+            None
+        );
+        assert_eq!(
+            parse_config_bytes(b"a\n    \xff\n").unwrap_err().line(),
+            Some(2)
+        );
+
+        assert_eq!(parse_config("a\n\tb\n    c\n").unwrap_err().line(), Some(3));
+    }
+
+    #[test]
+    fn error_with_source() {
+        let source = "a\n    b\n\tc\n";
+        let rendered = parse_config(source).unwrap_err().with_source(source);
+        let lines = rendered.lines().collect::<Vec<_>>();
+        assert_eq!(lines.len(), 3);
+        assert_eq!(lines[1], "3 | \tc");
+        assert_eq!(lines[2], "  | ^");
+
+        let source = "a\n    \"b\" c\n";
+        let rendered = parse_config(source).unwrap_err().with_source(source);
+        assert_eq!(
+            rendered,
+            "characters after string on line 2\n2 |     \"b\" c\n"
+        );
+
+        let err = parse_config_bytes(b"\xff").unwrap_err();
+        assert_eq!(err.with_source(""), format!("{}\n", err));
+    }
+
+    #[test]
+    fn documents() {
+        let source = "a\n    1\n---\r\nb\n    2\n    ---\n    3\n---\n";
+        let documents = parse_documents(source, "---").unwrap();
+        assert_eq!(documents.len(), 3);
+        assert_eq!(documents[0]["a"].value(), Some("1"));
+        assert_eq!(
+            documents[1]["b"].values().collect::<Vec<_>>(),
+            vec!["2", "---", "3"]
+        );
+        assert!(documents[2].is_empty());
+
+        let offset = documents[1]["b"]["3"].span().offset;
+        assert_eq!(&source[offset..offset + 1], "3");
+
+        let source = "a\n---\nb\n\t\"c\n";
+        let err = parse_documents(source, "---").unwrap_err();
+        assert_eq!(err.line(), Some(4));
+
+        let source = "a\n---\nb\n    c\n\td\n";
+        let err = parse_documents(source, "---").unwrap_err();
+        assert_eq!(err.line(), Some(5));
+        assert!(err.with_source(source).ends_with("5 | \td\n  | ^\n"));
+
+        for separator in ["", "-\n-"] {
+            assert_eq!(
+                parse_documents("a\n\nb\n", separator),
+                Err(NcclError::InvalidDocumentSeparator {
+                    separator: String::from(separator)
+                })
+            );
+        }
+    }
 }
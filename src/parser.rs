@@ -1,9 +1,13 @@
 //! Module containing the parser
 
 use crate::scanner::Scanner;
-use crate::scanner::{Token, TokenKind};
+use crate::scanner::{Span, Token, TokenKind};
 use crate::Config;
-use crate::NcclError;
+use crate::{DuplicatePolicy, NcclError, ParseOptions};
+
+use alloc::borrow::Cow;
+use alloc::format;
+use alloc::vec;
 
 /// The key of the top-level node.
 pub const TOP_LEVEL_KEY: &str = "__top_level__";
@@ -40,6 +44,13 @@ impl Indent {
         }
     }
 
+    fn level(&self) -> usize {
+        match self {
+            Indent::TopLevel => 0,
+            &Indent::Tabs { level } | &Indent::Spaces { level, .. } => level,
+        }
+    }
+
     fn increase_tabs(&self) -> Indent {
         match self {
             Indent::TopLevel => Indent::Tabs { level: 1 },
@@ -72,46 +83,93 @@ impl Indent {
 }
 
 pub(crate) fn parse<'a>(scanner: &mut Scanner<'a>) -> Result<Config<'a>, NcclError> {
-    parse_with(scanner, &Config::new(TOP_LEVEL_KEY, None))
+    parse_with(
+        scanner,
+        Config::new(TOP_LEVEL_KEY, None),
+        ParseOptions::default(),
+    )
 }
 
 pub(crate) fn parse_with<'a>(
     scanner: &mut Scanner<'a>,
-    original: &Config<'a>,
+    mut config: Config<'a>,
+    options: ParseOptions,
 ) -> Result<Config<'a>, NcclError> {
-    let mut config = original.clone();
+    let mut nodes = 0;
 
     while scanner.peek_token(0)?.kind != TokenKind::Eof {
-        parse_kv(scanner, Indent::TopLevel, &mut config)?;
+        parse_kv(scanner, Indent::TopLevel, &mut config, &options, &mut nodes)?;
     }
 
+    // comments after the last key belong to the whole config
+    let trailing = scanner.peek_token(0)?.comment;
+    config.trailing_comment = join_comments(config.trailing_comment.take(), trailing);
+
     Ok(config)
 }
 
+// a key whose children are being parsed
+struct Frame<'a> {
+    node: Config<'a>,
+    // the indentation of the node's children and the token that starts one
+    children: Option<(Indent, TokenKind)>,
+}
+
+// keys are kept on an explicit stack rather than parsed recursively, so
+// deeply nested configs can't overflow the call stack
 fn parse_kv<'a>(
     scanner: &mut Scanner<'a>,
     indent: Indent,
     parent: &mut Config<'a>,
+    options: &ParseOptions,
+    nodes: &mut usize,
 ) -> Result<(), NcclError> {
-    let value = consume_value(scanner)?;
-    let mut node = {
-        if parent.has_value(value.lexeme) {
-            parent[value.lexeme].clone()
-        } else if let TokenKind::QuotedValue(kind) = value.kind {
-            Config::new_with_span(value.lexeme, value.span, Some(kind))
-        } else {
-            Config::new_with_span(value.lexeme, value.span, None)
+    let node = parse_node(scanner, indent, parent, options, nodes)?;
+    let mut stack = vec![open_frame(scanner, indent, node, options)?];
+
+    while let Some(frame) = stack.last_mut() {
+        match frame.children {
+            Some((next_indent, token)) if peek_indent(scanner, options)? == token => {
+                scanner.next_token()?;
+                let node = parse_node(scanner, next_indent, &mut frame.node, options, nodes)?;
+                stack.push(open_frame(scanner, next_indent, node, options)?);
+            }
+
+            _ => {
+                let node = stack.pop().unwrap().node;
+                stack
+                    .last_mut()
+                    .map_or(&mut *parent, |frame| &mut frame.node)
+                    .add_child(node);
+            }
         }
-    };
+    }
+
+    Ok(())
+}
 
-    match scanner.peek_token(0)?.kind {
+// check the indentation following a key to see if it has children
+fn open_frame<'a>(
+    scanner: &mut Scanner<'a>,
+    indent: Indent,
+    mut node: Config<'a>,
+    options: &ParseOptions,
+) -> Result<Frame<'a>, NcclError> {
+    let mut children = None;
+
+    match peek_indent(scanner, options)? {
         TokenKind::Tabs(tabs) if indent.is_tabs_or_top_level() => {
             let next_indent = indent.increase_tabs();
             if tabs == next_indent.level_tabs() {
-                while scanner.peek_token(0)?.kind == TokenKind::Tabs(next_indent.level_tabs()) {
-                    consume(scanner, TokenKind::Tabs(next_indent.level_tabs())).unwrap();
-                    parse_kv(scanner, next_indent, &mut node)?;
-                }
+                check_depth(scanner, next_indent, options)?;
+                remember_indent(scanner, indent, &mut node)?;
+                children = Some((next_indent, TokenKind::Tabs(next_indent.level_tabs())));
+            } else if tabs > next_indent.level_tabs() {
+                return Err(NcclError::UnexpectedIndent {
+                    span: scanner.peek_token(0)?.span,
+                    expected: TokenKind::Tabs(next_indent.level_tabs()),
+                    got: TokenKind::Tabs(tabs),
+                });
             }
         }
 
@@ -119,20 +177,198 @@ fn parse_kv<'a>(
         TokenKind::Spaces(spaces) if indent.is_spaces_or_top_level() => {
             let next_indent = indent.increase_spaces(indent.width().unwrap_or(spaces));
             if spaces == next_indent.level_spaces() {
-                while scanner.peek_token(0)?.kind == TokenKind::Spaces(next_indent.level_spaces()) {
-                    consume(scanner, TokenKind::Spaces(next_indent.level_spaces())).unwrap();
-                    parse_kv(scanner, next_indent, &mut node)?;
-                }
+                check_depth(scanner, next_indent, options)?;
+                remember_indent(scanner, indent, &mut node)?;
+                children = Some((next_indent, TokenKind::Spaces(next_indent.level_spaces())));
+            } else if spaces > indent.level_spaces() {
+                return Err(NcclError::UnexpectedIndent {
+                    span: scanner.peek_token(0)?.span,
+                    expected: TokenKind::Spaces(next_indent.level_spaces()),
+                    got: TokenKind::Spaces(spaces),
+                });
             }
         }
 
+        TokenKind::Spaces(_) if matches!(indent, Indent::Tabs { .. }) => {
+            let token = scanner.peek_token(0)?;
+            return Err(NcclError::InconsistentIndentation {
+                span: token.span,
+                expected: TokenKind::Tabs(indent.level_tabs()),
+                got: token.kind,
+            });
+        }
+
+        TokenKind::Tabs(_) if matches!(indent, Indent::Spaces { .. }) => {
+            let token = scanner.peek_token(0)?;
+            return Err(NcclError::InconsistentIndentation {
+                span: token.span,
+                expected: TokenKind::Spaces(indent.level_spaces()),
+                got: token.kind,
+            });
+        }
+
         _ => {}
     }
 
-    parent.add_child(node);
+    Ok(Frame { node, children })
+}
+
+// the key of a key-value pair
+fn parse_node<'a>(
+    scanner: &mut Scanner<'a>,
+    indent: Indent,
+    parent: &mut Config<'a>,
+    options: &ParseOptions,
+    nodes: &mut usize,
+) -> Result<Config<'a>, NcclError> {
+    let mut value = consume_value(scanner)?;
+    let inline = match (options.inline_separator, indent, value.kind) {
+        (Some(separator), Indent::TopLevel, TokenKind::Value) => {
+            split_inline(scanner, &mut value, separator)?
+        }
+        _ => None,
+    };
+
+    let mut node = {
+        if parent.has_value(&value.lexeme) {
+            if options.duplicate_keys == DuplicatePolicy::Error {
+                return Err(NcclError::DuplicateKey { span: value.span });
+            }
+
+            // take the existing node rather than cloning it, leaving an empty
+            // one to hold its place until the node is added back
+            let placeholder = Config::new_with_span(value.lexeme.clone(), value.span, None);
+            let mut node = core::mem::replace(&mut parent[&*value.lexeme], placeholder);
+            node.comment = join_comments(node.comment.take(), value.comment);
+            node
+        } else {
+            count_node(value.span, options, nodes)?;
+            let quotes = match value.kind {
+                TokenKind::QuotedValue(kind) => Some(kind),
+                _ => None,
+            };
+            let mut node = Config::new_with_span(value.lexeme, value.span, quotes);
+            node.comment = value.comment.map(Cow::Borrowed);
+            node.comment_char = options.comment_char;
+            node
+        }
+    };
+
+    if let Some(inline) = inline {
+        if !node.has_value(&inline.lexeme) {
+            count_node(inline.span, options, nodes)?;
+            let mut child = Config::new_with_span(inline.lexeme, inline.span, None);
+            child.comment_char = options.comment_char;
+            node.add_child(child);
+        }
+    }
+
+    Ok(node)
+}
+
+// a repeated key keeps the comments above each of its occurrences
+fn join_comments<'a>(existing: Option<Cow<'a, str>>, new: Option<&'a str>) -> Option<Cow<'a, str>> {
+    match (existing, new) {
+        (Some(existing), Some(new)) => Some(Cow::Owned(format!("{}\n{}", existing, new))),
+        (existing, None) => existing,
+        (None, new) => new.map(Cow::Borrowed),
+    }
+}
+
+// top-level keys keep the indentation of their first child so it can be
+// printed the same way
+fn remember_indent<'a>(
+    scanner: &mut Scanner<'a>,
+    indent: Indent,
+    node: &mut Config<'a>,
+) -> Result<(), NcclError> {
+    if matches!(indent, Indent::TopLevel) && node.indent_unit.is_none() {
+        let span = scanner.peek_token(0)?.span;
+        node.indent_unit = Some(scanner.source_of(span)?);
+    }
     Ok(())
 }
 
+fn count_node(span: Span, options: &ParseOptions, nodes: &mut usize) -> Result<(), NcclError> {
+    *nodes += 1;
+    match options.max_nodes {
+        Some(limit) if *nodes > limit => Err(NcclError::TooManyNodes { span, limit }),
+        _ => Ok(()),
+    }
+}
+
+// split a `key: value` line at the separator, leaving the key in `token` and
+// returning the value
+//
+// the separator is never the comment character or a backslash, so the line
+// is split as written and each half is unescaped separately
+fn split_inline<'a>(
+    scanner: &Scanner<'a>,
+    token: &mut Token<'a>,
+    separator: char,
+) -> Result<Option<Token<'a>>, NcclError> {
+    let source = scanner.source_of(token.span)?;
+    let Some((key, value)) = source.split_once(separator) else {
+        return Ok(None);
+    };
+    let key = key.trim_end();
+    let value = value.trim();
+    if key.is_empty() {
+        return Ok(None);
+    }
+
+    let value_start = value.as_ptr() as usize - source.as_ptr() as usize;
+    let value_end = value_start + value.len();
+    let inline = Token {
+        kind: TokenKind::Value,
+        lexeme: scanner.unescape_comment(value),
+        span: Span {
+            line: token.span.line,
+            column: token.span.column - source[value_end..].chars().count(),
+            offset: token.span.offset + value_start,
+            len: value.len(),
+        },
+        comment: None,
+    };
+
+    token.span.column -= source[key.len()..].chars().count();
+    token.span.len = key.len();
+    token.lexeme = scanner.unescape_comment(key);
+
+    if value.is_empty() {
+        Ok(None)
+    } else {
+        Ok(Some(inline))
+    }
+}
+
+// the kind of the next token, with tabs converted to spaces if
+// ParseOptions::tab_width is set
+fn peek_indent(scanner: &mut Scanner<'_>, options: &ParseOptions) -> Result<TokenKind, NcclError> {
+    let token = scanner.peek_token(0)?;
+    match (token.kind, options.tab_width) {
+        (TokenKind::Tabs(tabs), Some(width)) => match tabs.checked_mul(width) {
+            Some(spaces) => Ok(TokenKind::Spaces(spaces)),
+            None => Err(NcclError::IndentOverflow { span: token.span }),
+        },
+        (kind, _) => Ok(kind),
+    }
+}
+
+fn check_depth(
+    scanner: &mut Scanner<'_>,
+    indent: Indent,
+    options: &ParseOptions,
+) -> Result<(), NcclError> {
+    match options.max_depth {
+        Some(limit) if indent.level() > limit => Err(NcclError::MaxDepthExceeded {
+            span: scanner.peek_token(0)?.span,
+            limit,
+        }),
+        _ => Ok(()),
+    }
+}
+
 fn consume_value<'a>(scanner: &mut Scanner<'a>) -> Result<Token<'a>, NcclError> {
     let tok = scanner.next_token()?;
     match tok.kind {
@@ -145,19 +381,6 @@ fn consume_value<'a>(scanner: &mut Scanner<'a>) -> Result<Token<'a>, NcclError>
     }
 }
 
-fn consume<'a>(scanner: &mut Scanner<'a>, kind: TokenKind) -> Result<Token<'a>, NcclError> {
-    let tok = scanner.next_token()?;
-    if tok.kind == kind {
-        Ok(tok)
-    } else {
-        Err(NcclError::UnexpectedToken {
-            span: tok.span,
-            expected: kind,
-            got: tok.kind,
-        })
-    }
-}
-
 #[cfg(test)]
 mod test {
     use super::*;
@@ -169,7 +392,7 @@ mod test {
                 #[allow(unused_mut)]
                 let mut set = crate::config::make_map();
                 $(
-                    set.insert($key, $item);
+                    set.insert($key.into(), $item);
                 )*
                 set
             }
@@ -185,42 +408,70 @@ mod test {
             config,
             Config {
                 quotes: None,
-                key: TOP_LEVEL_KEY,
+                key: TOP_LEVEL_KEY.into(),
                 span: Span::default(),
+                comment: None,
+                trailing_comment: None,
+                indent_unit: None,
+                comment_char: '#',
                 value: map![
                     "jackson" => Config {
                         quotes: None,
-                        key: "jackson",
+                        key: "jackson".into(),
                         span: Span::default(),
+                        comment: None,
+                        trailing_comment: None,
+                        indent_unit: None,
+                        comment_char: '#',
                         value: map![
                             "easy" => Config {
                                 quotes: None,
-                                key: "easy",
+                                key: "easy".into(),
                                 span: Span::default(),
+                                comment: None,
+                                trailing_comment: None,
+                                indent_unit: None,
+                                comment_char: '#',
                                 value: map![
                                     "abc" => Config {
                                         quotes: None,
-                                        key: "abc",
+                                        key: "abc".into(),
                                         span: Span::default(),
+                                        comment: None,
+                                        trailing_comment: None,
+                                        indent_unit: None,
+                                        comment_char: '#',
                                         value: map![]
                                     },
                                     "123" => Config {
                                         quotes: None,
-                                        key: "123",
+                                        key: "123".into(),
                                         span: Span::default(),
+                                        comment: None,
+                                        trailing_comment: None,
+                                        indent_unit: None,
+                                        comment_char: '#',
                                         value: map![]
                                     }
                                 ]
                             },
                             "hopefully" => Config {
                                 quotes: None,
-                                key: "hopefully",
+                                key: "hopefully".into(),
                                 span: Span::default(),
+                                comment: None,
+                                trailing_comment: None,
+                                indent_unit: None,
+                                comment_char: '#',
                                 value: map![
                                     "tabs work" => Config {
                                         quotes: None,
-                                        key: "tabs work",
+                                        key: "tabs work".into(),
                                         span: Span::default(),
+                                        comment: None,
+                                        trailing_comment: None,
+                                        indent_unit: None,
+                                        comment_char: '#',
                                         value: map![]
                                     }
                                 ]
@@ -241,61 +492,101 @@ mod test {
             config,
             Config {
                 quotes: None,
-                key: TOP_LEVEL_KEY,
+                key: TOP_LEVEL_KEY.into(),
                 span: Span::default(),
+                comment: None,
+                trailing_comment: None,
+                indent_unit: None,
+                comment_char: '#',
                 value: map![
                     "server" => Config {
                         quotes: None,
-                        key: "server",
+                        key: "server".into(),
                         span: Span::default(),
+                        comment: None,
+                        trailing_comment: None,
+                        indent_unit: None,
+                        comment_char: '#',
                         value: map![
                             "domain" => Config {
                                 quotes: None,
-                                key: "domain",
+                                key: "domain".into(),
                                 span: Span::default(),
+                                comment: None,
+                                trailing_comment: None,
+                                indent_unit: None,
+                                comment_char: '#',
                                 value: map![
                                     "example.com" => Config {
                                         quotes: None,
-                                        key: "example.com",
+                                        key: "example.com".into(),
                                         span: Span::default(),
+                                        comment: None,
+                                        trailing_comment: None,
+                                        indent_unit: None,
+                                        comment_char: '#',
                                         value: map![]
                                     },
                                     "www.example.com" => Config {
                                         quotes: None,
-                                        key: "www.example.com",
+                                        key: "www.example.com".into(),
                                         span: Span::default(),
+                                        comment: None,
+                                        trailing_comment: None,
+                                        indent_unit: None,
+                                        comment_char: '#',
                                         value: map![]
                                     }
                                 ]
                             },
                             "port" => Config {
                                 quotes: None,
-                                key: "port",
+                                key: "port".into(),
                                 span: Span::default(),
+                                comment: None,
+                                trailing_comment: None,
+                                indent_unit: None,
+                                comment_char: '#',
                                 value: map![
                                     "80" => Config {
                                         quotes: None,
-                                        key: "80",
+                                        key: "80".into(),
                                         span: Span::default(),
+                                        comment: None,
+                                        trailing_comment: None,
+                                        indent_unit: None,
+                                        comment_char: '#',
                                         value: map![]
                                     },
                                     "443" => Config {
                                         quotes: None,
-                                        key: "443",
+                                        key: "443".into(),
                                         span: Span::default(),
+                                        comment: None,
+                                        trailing_comment: None,
+                                        indent_unit: None,
+                                        comment_char: '#',
                                         value: map![]
                                     }
                                 ]
                             },
                             "root" => Config {
                                 quotes: None,
-                                key: "root",
+                                key: "root".into(),
                                 span: Span::default(),
+                                comment: None,
+                                trailing_comment: None,
+                                indent_unit: None,
+                                comment_char: '#',
                                 value: map![
                                     "/var/www/html" => Config {
                                         quotes: None,
-                                        key: "/var/www/html",
+                                        key: "/var/www/html".into(),
                                         span: Span::default(),
+                                        comment: None,
+                                        trailing_comment: None,
+                                        indent_unit: None,
+                                        comment_char: '#',
                                         value: map![]
                                     }
                                 ]
@@ -334,4 +625,273 @@ mod test {
             parse(&mut scanner).unwrap_err();
         }
     }
+
+    #[test]
+    fn inconsistent_indentation() {
+        let source = std::fs::read_to_string("examples/bad/mix-indent.nccl").unwrap();
+        let mut scanner = Scanner::new(&source);
+        match parse(&mut scanner) {
+            Err(NcclError::InconsistentIndentation {
+                span,
+                expected: TokenKind::Tabs(1),
+                got: TokenKind::Spaces(4),
+            }) => assert_eq!(span.line, 3),
+            result => panic!("expected inconsistent indentation, got {:?}", result),
+        }
+
+        let source = "hello\n    world\n\toops!\n";
+        let mut scanner = Scanner::new(source);
+        assert!(matches!(
+            parse(&mut scanner),
+            Err(NcclError::InconsistentIndentation {
+                expected: TokenKind::Spaces(4),
+                got: TokenKind::Tabs(1),
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    fn max_depth() {
+        let mut source = String::new();
+        for level in 0..10_000 {
+            source.push_str(&"\t".repeat(level));
+            source.push_str("a\n");
+        }
+
+        let mut scanner = Scanner::new(&source);
+        match parse(&mut scanner) {
+            Err(NcclError::MaxDepthExceeded { span, limit: 256 }) => assert_eq!(span.line, 258),
+            result => panic!("expected max depth error, got {:?}", result),
+        }
+
+        let options = ParseOptions {
+            max_depth: Some(2),
+            ..Default::default()
+        };
+        let mut scanner = Scanner::new("a\n b\n  c\n");
+        let config = parse_with(&mut scanner, Config::new(TOP_LEVEL_KEY, None), options);
+        assert!(config.is_ok());
+
+        let mut scanner = Scanner::new("a\n b\n  c\n   d\n");
+        let config = parse_with(&mut scanner, Config::new(TOP_LEVEL_KEY, None), options);
+        assert!(matches!(
+            config,
+            Err(NcclError::MaxDepthExceeded { limit: 2, .. })
+        ));
+    }
+
+    #[test]
+    fn deep_nesting() {
+        const DEPTH: usize = 50_000;
+
+        // each level is indented one more tab than the last, so the source
+        // grows quadratically and is over a gigabyte
+        let mut source = String::with_capacity(DEPTH * (DEPTH + 3) / 2);
+        for level in 0..DEPTH {
+            source.push_str(&"\t".repeat(level));
+            source.push_str("a\n");
+        }
+
+        let options = ParseOptions {
+            max_depth: None,
+            ..Default::default()
+        };
+        let mut scanner = Scanner::new(&source);
+        let config = parse_with(&mut scanner, Config::new(TOP_LEVEL_KEY, None), options).unwrap();
+
+        let mut node = &config;
+        let mut depth = 0;
+        while let Some(child) = node.child() {
+            node = child;
+            depth += 1;
+        }
+        assert_eq!(depth, DEPTH);
+        assert_eq!(node.span().line, DEPTH);
+
+        // and the config is dropped without recursing
+    }
+
+    #[test]
+    fn skipped_indent_level() {
+        let source = "a\n    b\n            c\nd\n";
+        match parse(&mut Scanner::new(source)) {
+            Err(NcclError::UnexpectedIndent {
+                span,
+                expected: TokenKind::Spaces(8),
+                got: TokenKind::Spaces(12),
+            }) => assert_eq!(span.line, 3),
+            result => panic!("expected unexpected indent, got {:?}", result),
+        }
+
+        let source = "a\n\tb\n\t\t\tc\n";
+        assert!(matches!(
+            parse(&mut Scanner::new(source)),
+            Err(NcclError::UnexpectedIndent {
+                expected: TokenKind::Tabs(2),
+                got: TokenKind::Tabs(3),
+                ..
+            })
+        ));
+
+        let source = "a\n    b\n      c\n";
+        assert!(matches!(
+            parse(&mut Scanner::new(source)),
+            Err(NcclError::UnexpectedIndent {
+                expected: TokenKind::Spaces(8),
+                got: TokenKind::Spaces(6),
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    fn tab_width() {
+        let source = "a\n\tb\n    c\n\t\td\n        e\nf\n    g\n";
+        let options = ParseOptions {
+            tab_width: Some(4),
+            ..ParseOptions::default()
+        };
+
+        let config = parse_with(
+            &mut Scanner::new(source),
+            Config::new(TOP_LEVEL_KEY, None),
+            options,
+        )
+        .unwrap();
+        assert_eq!(config["a"].values().collect::<Vec<_>>(), vec!["b", "c"]);
+        assert_eq!(
+            config["a"]["c"].values().collect::<Vec<_>>(),
+            vec!["d", "e"]
+        );
+        assert_eq!(config["f"].value(), Some("g"));
+
+        assert!(matches!(
+            parse(&mut Scanner::new(source)),
+            Err(NcclError::InconsistentIndentation { .. })
+        ));
+
+        let options = ParseOptions {
+            tab_width: Some(0),
+            ..ParseOptions::default()
+        };
+        assert_eq!(
+            crate::parse_config_with_options(source, options),
+            Err(NcclError::InvalidTabWidth { tab_width: 0 })
+        );
+
+        let options = ParseOptions {
+            tab_width: Some(usize::MAX),
+            ..ParseOptions::default()
+        };
+        assert!(matches!(
+            crate::parse_config_with_options("a\n\t\tb\n", options),
+            Err(NcclError::IndentOverflow { span }) if span.line == 2
+        ));
+    }
+
+    #[test]
+    fn max_nodes() {
+        let mut source = String::from("wide\n");
+        for i in 0..10_000 {
+            source.push_str(&format!("    {}\n", i));
+        }
+
+        let options = ParseOptions {
+            max_nodes: Some(1000),
+            ..Default::default()
+        };
+        let mut scanner = Scanner::new(&source);
+        match parse_with(&mut scanner, Config::new(TOP_LEVEL_KEY, None), options) {
+            Err(NcclError::TooManyNodes { span, limit: 1000 }) => assert_eq!(span.line, 1001),
+            result => panic!("expected too many nodes, got {:?}", result),
+        }
+
+        // repeated keys are merged, so they don't count again
+        let options = ParseOptions {
+            max_nodes: Some(2),
+            ..Default::default()
+        };
+        let mut scanner = Scanner::new("a\n    b\na\n    b\n");
+        let config = parse_with(&mut scanner, Config::new(TOP_LEVEL_KEY, None), options);
+        assert!(config.is_ok());
+
+        assert!(parse(&mut Scanner::new(&source)).is_ok());
+    }
+
+    #[test]
+    fn empty_sources() {
+        let sources = [
+            std::fs::read_to_string("examples/empty.nccl").unwrap(),
+            String::new(),
+            String::from("   "),
+            String::from("\t"),
+            String::from("\n\n\n"),
+            String::from(" \t \n  \n\t\n"),
+            String::from("\r\n\r\n"),
+            String::from("# comment\n"),
+            String::from("# comment"),
+            String::from("   # indented\n\t# comment\n\n# more"),
+        ];
+
+        for source in sources {
+            let config = parse(&mut Scanner::new(&source)).unwrap();
+            assert!(config.is_empty(), "{:?}", source);
+            assert_eq!(config.key(), TOP_LEVEL_KEY);
+        }
+    }
+
+    #[test]
+    fn inline_separator() {
+        let source =
+            "name: nccl\nversion :  5\nempty:\nurl: http://example.com\n    mirror: also a value\n";
+        let options = ParseOptions {
+            inline_separator: Some(':'),
+            ..Default::default()
+        };
+
+        let config = parse_with(
+            &mut Scanner::new(source),
+            Config::new(TOP_LEVEL_KEY, None),
+            options,
+        )
+        .unwrap();
+        assert_eq!(config["name"].value(), Some("nccl"));
+        assert_eq!(config["version"].value(), Some("5"));
+        assert!(config["empty"].is_empty());
+        assert_eq!(
+            config["url"].values().collect::<Vec<_>>(),
+            vec!["http://example.com", "mirror: also a value"]
+        );
+
+        let value = config["version"].child().unwrap().span();
+        assert_eq!(&source[value.offset..value.offset + value.len], "5");
+        assert_eq!(value.line, 2);
+
+        let config = parse(&mut Scanner::new(source)).unwrap();
+        assert!(config.has_value("name: nccl"));
+
+        // each half leaves out the backslash of an escaped comment character,
+        // while its span still covers the backslash
+        let source = "\\#tag \\#1: my \\# value\n";
+        let config = crate::parse_config_with_options(source, options).unwrap();
+        assert_eq!(config["#tag #1"].value(), Some("my # value"));
+        let value = config["#tag #1"].child().unwrap().span();
+        assert_eq!(
+            &source[value.offset..value.offset + value.len],
+            "my \\# value"
+        );
+        assert_eq!(value.column, source.len() - 1);
+
+        for separator in [' ', '\t', '\n', '"', '\'', '\\', '#', '\0'] {
+            let options = ParseOptions {
+                inline_separator: Some(separator),
+                ..Default::default()
+            };
+            assert_eq!(
+                crate::parse_config_with_options(source, options),
+                Err(NcclError::InvalidInlineSeparator { separator })
+            );
+        }
+    }
 }
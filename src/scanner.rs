@@ -1,11 +1,12 @@
 //! Contains types relevant to scanning nccl sources
 
-use crate::NcclError;
+use crate::{NcclError, ParseOptions};
 
-use std::collections::VecDeque;
+use alloc::borrow::Cow;
+use alloc::collections::VecDeque;
 
 /// Types of quotes
-#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Clone, Copy)]
 #[cfg_attr(fuzzing, derive(arbitrary::Arbitrary))]
 pub enum QuoteKind {
     Single,
@@ -37,18 +38,78 @@ pub enum TokenKind {
 }
 
 /// A byte location in a source
+///
+/// The default span has every field set to 0. Since lines start from 1, it
+/// marks something that did not come from a source, like a node created with
+/// [`crate::Config::new`].
+///
+/// ```
+/// let config = nccl::Config::new("key", None);
+/// assert_eq!(config.span(), nccl::Span::default());
+/// ```
 #[derive(Debug, PartialEq, Eq, Copy, Clone, Default)]
 #[cfg_attr(fuzzing, derive(arbitrary::Arbitrary))]
 pub struct Span {
+    /// The line, starting from 1.
     pub line: usize,
+    /// The column at the end of the token, counted in characters.
     pub column: usize,
+    /// The byte offset of the start of the token in the source.
+    pub offset: usize,
+    /// The length of the token in bytes.
+    pub len: usize,
 }
 
+/// A token in a nccl source
 #[derive(Debug, PartialEq, Clone)]
-pub(crate) struct Token<'a> {
-    pub(crate) kind: TokenKind,
-    pub(crate) lexeme: &'a str,
-    pub(crate) span: Span,
+pub struct Token<'a> {
+    /// The kind of token.
+    pub kind: TokenKind,
+    /// The text of the token. Quoted values exclude their quotes, and an
+    /// unquoted value leaves out the backslash before an escaped comment
+    /// character.
+    pub lexeme: Cow<'a, str>,
+    /// The location of the token.
+    pub span: Span,
+    /// The comment lines before a value or the end of the source, if comments
+    /// are being kept.
+    pub comment: Option<&'a str>,
+}
+
+/// Iterate over the tokens of a nccl source
+///
+/// The last token is [`TokenKind::Eof`], unless an error is encountered, in
+/// which case the error is the last item.
+///
+/// ```
+/// # use nccl::scanner::{tokens, TokenKind};
+/// let kinds = tokens("key\n    value")
+///     .map(|token| token.map(|token| token.kind))
+///     .collect::<Result<Vec<_>, _>>()
+///     .unwrap();
+/// assert_eq!(
+///     kinds,
+///     vec![
+///         TokenKind::Value,
+///         TokenKind::Spaces(4),
+///         TokenKind::Value,
+///         TokenKind::Eof
+///     ]
+/// );
+/// ```
+pub fn tokens(source: &str) -> impl Iterator<Item = Result<Token<'_>, NcclError>> {
+    let mut scanner = Scanner::new(source);
+    let mut done = false;
+
+    core::iter::from_fn(move || {
+        if done {
+            return None;
+        }
+
+        let token = scanner.next_token();
+        done = !matches!(token, Ok(Token { kind, .. }) if kind != TokenKind::Eof);
+        Some(token)
+    })
 }
 
 pub(crate) struct Scanner<'a> {
@@ -58,6 +119,15 @@ pub(crate) struct Scanner<'a> {
     current: usize,
     pub(crate) line: usize,
     pub(crate) column: usize,
+    // where the source starts in a larger input, added to span offsets
+    pub(crate) base_offset: usize,
+    comment: u8,
+    max_value_len: Option<usize>,
+    keep_comments: bool,
+    c_comments: bool,
+    indent: Option<u8>,
+    // start and end of the comment lines since the last value
+    pending_comment: Option<(usize, usize)>,
 }
 
 impl<'a> Scanner<'a> {
@@ -69,7 +139,56 @@ impl<'a> Scanner<'a> {
             current: 0,
             line: 1,
             column: 0,
+            base_offset: 0,
+            comment: b'#',
+            max_value_len: None,
+            keep_comments: false,
+            c_comments: false,
+            indent: None,
+            pending_comment: None,
+        }
+    }
+
+    pub(crate) fn with_options(
+        source: &'a str,
+        options: &ParseOptions,
+    ) -> Result<Scanner<'a>, NcclError> {
+        // anything else would be mistaken for indentation, part of a value, or
+        // the start of a string or escape
+        let comment_char = options.comment_char;
+        if !comment_char.is_ascii_punctuation() || matches!(comment_char, '"' | '\'' | '\\') {
+            return Err(NcclError::InvalidCommentChar { comment_char });
         }
+        // tabs, newlines, and carriage returns already mean something else
+        if let Some(indent_char) = options
+            .indent_char
+            .filter(|&indent| !matches!(indent, ' ' | '\x0B' | '\x0C') || indent == comment_char)
+        {
+            return Err(NcclError::InvalidIndentChar { indent_char });
+        }
+
+        if let Some(tab_width @ 0) = options.tab_width {
+            return Err(NcclError::InvalidTabWidth { tab_width });
+        }
+
+        // these would split every key, or never appear in an unquoted one
+        if let Some(separator) = options.inline_separator.filter(|&separator| {
+            separator.is_whitespace()
+                || separator.is_control()
+                || matches!(separator, '"' | '\'' | '\\')
+                || separator == comment_char
+        }) {
+            return Err(NcclError::InvalidInlineSeparator { separator });
+        }
+
+        Ok(Scanner {
+            comment: options.comment_char as u8,
+            max_value_len: options.max_value_len,
+            keep_comments: options.keep_comments,
+            c_comments: options.c_comments,
+            indent: options.indent_char.map(|indent| indent as u8),
+            ..Scanner::new(source)
+        })
     }
 
     #[cfg(test)]
@@ -110,9 +229,7 @@ impl<'a> Scanner<'a> {
                 }
 
                 b'\n' | b'\r' => {
-                    self.column = 0;
-                    self.line += 1;
-                    self.advance_char();
+                    self.newline();
                     self.start = self.current;
                 }
 
@@ -123,37 +240,35 @@ impl<'a> Scanner<'a> {
                         tabs += 1;
                     }
 
-                    if self.peek_char() == b'#'
-                        || self.peek_char() == b'\n'
-                        || self.peek_char() == b'\r'
-                    {
-                        self.until_newline();
+                    if self.rest_is_blank() {
+                        self.skip_blank()?;
+                    } else if self.is_space(self.peek_char()) {
+                        return Err(self.mixed_indentation());
                     } else {
                         self.add_token(TokenKind::Tabs(tabs))?;
                         break;
                     }
                 }
 
-                b' ' => {
+                byte if self.is_space(byte) => {
                     let mut spaces = 0;
-                    while self.peek_char() == b' ' {
+                    while self.is_space(self.peek_char()) {
                         self.advance_char();
                         spaces += 1;
                     }
 
-                    if self.peek_char() == b'#'
-                        || self.peek_char() == b'\n'
-                        || self.peek_char() == b'\r'
-                    {
-                        self.until_newline();
+                    if self.rest_is_blank() {
+                        self.skip_blank()?;
+                    } else if self.peek_char() == b'\t' {
+                        return Err(self.mixed_indentation());
                     } else {
                         self.add_token(TokenKind::Spaces(spaces))?;
                         break;
                     }
                 }
 
-                b'#' => {
-                    self.until_newline();
+                _ if self.at_comment(self.current) => {
+                    self.comment_line()?;
                 }
 
                 _ => break,
@@ -165,6 +280,15 @@ impl<'a> Scanner<'a> {
         match self.peek_char() {
             quote @ (b'"' | b'\'') => self.string(quote)?,
 
+            // a value starting with an escaped comment character or space,
+            // leave out the backslash
+            b'\\' if self.peek_next_char() == self.comment || self.peek_next_char() == b' ' => {
+                self.advance_char();
+                self.start = self.current;
+                self.until_newline();
+                self.add_token(TokenKind::Value)?;
+            }
+
             _ => {
                 self.until_newline();
                 self.add_token(TokenKind::Value)?;
@@ -189,12 +313,13 @@ impl<'a> Scanner<'a> {
             if self.peek_char() == b'\\' {
                 self.advance_char();
                 match self.peek_char() {
-                    b'n' | b'r' | b'\\' | b'"' => {}
+                    b'n' | b'r' | b't' | b'0' | b'a' | b'b' | b'f' | b'v' | b'\\' | b'"'
+                    | b'\'' => {}
+
+                    b'u' => self.unicode_escape()?,
 
                     b'\r' | b'\n' => {
-                        self.line += 1;
-                        self.column = 0;
-                        self.advance_char();
+                        self.newline();
                         while self.peek_char() == b' ' || self.peek_char() == b'\t' {
                             self.advance_char();
                         }
@@ -237,13 +362,158 @@ impl<'a> Scanner<'a> {
         }
 
         if self.peek_char() == b'\n' || self.peek_char() == b'\r' {
-            self.line += 1;
-            self.column = 0;
+            self.newline();
+        } else if self.at_comment(self.current) {
+            self.skip_comment()?;
+        } else {
+            return Err(NcclError::TrailingCharacters { line: self.line });
+        }
+
+        Ok(())
+    }
+
+    fn unicode_escape(&mut self) -> Result<(), NcclError> {
+        let line = self.line;
+
+        // \u{1F600}
+        //  ^
+        self.advance_char();
+        if self.peek_char() != b'{' {
+            return Err(NcclError::InvalidUnicodeEscape { line });
+        }
+        self.advance_char();
+
+        let start = self.current;
+        while self.peek_char().is_ascii_hexdigit() {
+            self.advance_char();
+        }
+
+        // leave the closing brace for the caller to consume
+        if self.peek_char() != b'}' || self.current == start {
+            return Err(NcclError::InvalidUnicodeEscape { line });
+        }
+
+        core::str::from_utf8(&self.source[start..self.current])
+            .ok()
+            .and_then(|hex| u32::from_str_radix(hex, 16).ok())
+            .and_then(char::from_u32)
+            .map(|_| ())
+            .ok_or(NcclError::InvalidUnicodeEscape { line })
+    }
+
+    // a tab directly after spaces in indentation or the other way around,
+    // which would otherwise end up at the start of the value
+    fn mixed_indentation(&mut self) -> NcclError {
+        while self.is_space(self.peek_char()) || self.peek_char() == b'\t' {
+            self.advance_char();
+        }
+
+        NcclError::MixedIndentation {
+            span: Span {
+                line: self.line,
+                column: self.column,
+                offset: self.base_offset + self.start,
+                len: self.current - self.start,
+            },
+        }
+    }
+
+    // consume a line break, treating \r\n as one
+    fn newline(&mut self) {
+        if self.advance_char() == b'\r' && self.peek_char() == b'\n' {
+            self.advance_char();
+        }
+        self.line += 1;
+    }
+
+    // whether the rest of the line is only whitespace and maybe a comment
+    fn rest_is_blank(&self) -> bool {
+        match self.source[self.current..]
+            .iter()
+            .position(|&byte| !self.is_space(byte) && byte != b'\t')
+        {
+            None => true,
+            Some(i) => {
+                let byte = self.source[self.current + i];
+                byte == b'\n' || byte == b'\r' || self.at_comment(self.current + i)
+            }
+        }
+    }
+
+    fn skip_blank(&mut self) -> Result<(), NcclError> {
+        while self.is_space(self.peek_char()) || self.peek_char() == b'\t' {
             self.advance_char();
-        } else if self.peek_char() == b'#' {
+        }
+
+        if self.at_comment(self.current) {
+            self.comment_line()
+        } else {
+            self.until_newline();
+            Ok(())
+        }
+    }
+
+    // whether the byte counts as a space for indentation
+    fn is_space(&self, byte: u8) -> bool {
+        byte == b' ' || Some(byte) == self.indent
+    }
+
+    // whether a comment starts at the byte index
+    fn at_comment(&self, at: usize) -> bool {
+        match self.source.get(at..at + 2) {
+            _ if self.source.get(at) == Some(&self.comment) => true,
+            Some(b"//" | b"/*") => self.c_comments,
+            _ => false,
+        }
+    }
+
+    // consume a comment, which must be at the current position
+    fn skip_comment(&mut self) -> Result<(), NcclError> {
+        if !self.c_comments || self.source.get(self.current..self.current + 2) != Some(b"/*") {
             self.until_newline();
+            return Ok(());
+        }
+
+        let start = self.line;
+        self.advance_char();
+        self.advance_char();
+
+        while self.source.get(self.current..self.current + 2) != Some(b"*/") {
+            if self.is_at_end() {
+                return Err(NcclError::UnterminatedComment { start });
+            }
+
+            if self.peek_char() == b'\n' {
+                self.line += 1;
+            }
+            self.advance_char();
+        }
+
+        self.advance_char();
+        self.advance_char();
+
+        // like a quoted value, nothing may follow a block comment on its line
+        // except whitespace or another comment
+        while self.peek_char() == b' ' || self.peek_char() == b'\t' {
+            self.advance_char();
+        }
+
+        if self.at_comment(self.current) {
+            self.skip_comment()
+        } else if self.peek_char() == b'\n' || self.peek_char() == b'\r' || self.is_at_end() {
+            Ok(())
         } else {
-            return Err(NcclError::TrailingCharacters { line: self.line });
+            Err(NcclError::TrailingCharacters { line: self.line })
+        }
+    }
+
+    fn comment_line(&mut self) -> Result<(), NcclError> {
+        let start = self.current;
+        self.skip_comment()?;
+
+        if self.keep_comments {
+            let start = self.pending_comment.map_or(start, |(start, _)| start);
+            self.pending_comment = Some((start, self.current));
         }
 
         Ok(())
@@ -260,14 +530,29 @@ impl<'a> Scanner<'a> {
     }
 
     fn advance_char(&mut self) -> u8 {
-        self.column += 1;
+        let byte = self.source[self.current];
         self.current += 1;
-        self.source[self.current - 1]
+
+        // count columns in characters rather than bytes, so skip utf-8
+        // continuation bytes
+        if byte == b'\n' || byte == b'\r' {
+            self.column = 0;
+        } else if !is_continuation(byte) {
+            self.column += 1;
+        }
+
+        byte
     }
 
     fn reverse_char(&mut self) -> u8 {
         self.current -= 1;
-        self.source[self.current]
+        let byte = self.source[self.current];
+
+        if byte != b'\n' && byte != b'\r' && !is_continuation(byte) {
+            self.column -= 1;
+        }
+
+        byte
     }
 
     fn peek_char(&self) -> u8 {
@@ -278,32 +563,97 @@ impl<'a> Scanner<'a> {
         }
     }
 
+    fn peek_next_char(&self) -> u8 {
+        if self.current + 1 >= self.source.len() {
+            b'\0'
+        } else {
+            self.source[self.current + 1]
+        }
+    }
+
+    fn str_at(&self, start: usize, end: usize) -> Result<&'a str, NcclError> {
+        core::str::from_utf8(&self.source[start..end])
+            .map_err(|err| NcclError::utf8(self.source, start, err))
+    }
+
+    // the text of a value, leaving out the backslash of each escaped comment
+    // character
+    pub(crate) fn unescape_comment(&self, value: &'a str) -> Cow<'a, str> {
+        let escaped = [b'\\', self.comment];
+        let escaped = core::str::from_utf8(&escaped).expect("the comment character is ascii");
+        if value.contains(escaped) {
+            Cow::Owned(value.replace(escaped, &escaped[1..]))
+        } else {
+            Cow::Borrowed(value)
+        }
+    }
+
+    // the source text of a token
+    pub(crate) fn source_of(&self, span: Span) -> Result<&'a str, NcclError> {
+        let start = span.offset - self.base_offset;
+        self.str_at(start, start + span.len)
+    }
+
     fn add_token(&mut self, kind: TokenKind) -> Result<(), NcclError> {
-        let lexeme = std::str::from_utf8(&self.source[self.start..self.current])?;
+        let lexeme = self.str_at(self.start, self.current)?;
+        let lexeme = match kind {
+            TokenKind::Value => self.unescape_comment(lexeme),
+            _ => Cow::Borrowed(lexeme),
+        };
+        let span = Span {
+            line: self.line,
+            column: self.column,
+            offset: self.base_offset + self.start,
+            len: self.current - self.start,
+        };
+
+        if let (TokenKind::Value | TokenKind::QuotedValue(_), Some(limit)) =
+            (kind, self.max_value_len)
+        {
+            if lexeme.len() > limit {
+                return Err(NcclError::ValueTooLong { span, limit });
+            }
+        }
+
+        let comment = match kind {
+            TokenKind::Value | TokenKind::QuotedValue(_) | TokenKind::Eof => self
+                .pending_comment
+                .take()
+                .map(|(start, end)| self.str_at(start, end))
+                .transpose()?,
+            _ => None,
+        };
 
         self.tokens.push_back(Token {
             kind,
             lexeme,
-            span: Span {
-                line: self.line,
-                column: self.column,
-            },
+            span,
+            comment,
         });
 
         Ok(())
     }
 }
 
+pub(crate) fn is_continuation(byte: u8) -> bool {
+    byte & 0b1100_0000 == 0b1000_0000
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
 
+    // lexemes with an escaped comment character are owned, which
+    // escaped_comment checks separately
     fn get_all(source: &str) -> Vec<(TokenKind, &str)> {
         Scanner::new(source)
             .scan_all()
             .unwrap()
             .into_iter()
-            .map(|token| (token.kind, token.lexeme))
+            .map(|token| match token.lexeme {
+                Cow::Borrowed(lexeme) => (token.kind, lexeme),
+                Cow::Owned(lexeme) => panic!("unexpected owned lexeme {:?}", lexeme),
+            })
             .collect::<Vec<_>>()
     }
 
@@ -345,6 +695,30 @@ mod test {
         );
     }
 
+    #[test]
+    fn escaped_quotes() {
+        use super::TokenKind::*;
+
+        let tokens = get_all("'it\\'s here'\n\"say \\\"hi\\\"\"\n");
+        assert_eq!(
+            tokens,
+            vec![
+                (QuotedValue(QuoteKind::Single), "it\\'s here"),
+                (QuotedValue(QuoteKind::Double), "say \\\"hi\\\""),
+                (Eof, ""),
+            ]
+        );
+
+        let config = crate::parse_config("'it\\'s here'\n\"say \\\"hi\\\"\"\n").unwrap();
+        assert_eq!(
+            Ok(vec![String::from("it's here"), String::from("say \"hi\"")]),
+            config
+                .children()
+                .map(|value| value.parse_quoted())
+                .collect::<Result<Vec<_>, _>>()
+        );
+    }
+
     #[test]
     fn new_scan() {
         use super::TokenKind::*;
@@ -371,4 +745,258 @@ mod test {
             ]
         );
     }
+
+    #[test]
+    fn escaped_comment() {
+        use super::TokenKind::*;
+
+        let tokens = Scanner::new("\\#one\n\t\\# two\n  a \\# b\n\\\n")
+            .scan_all()
+            .unwrap()
+            .into_iter()
+            .map(|token| (token.kind, token.lexeme))
+            .collect::<Vec<_>>();
+        #[rustfmt::skip]
+        assert_eq!(
+            tokens,
+            vec![
+                (Value, Cow::Borrowed("#one")),
+                (Tabs(1), Cow::Borrowed("\t")), (Value, Cow::Borrowed("# two")),
+                (Spaces(2), Cow::Borrowed("  ")), (Value, Cow::Owned(String::from("a # b"))),
+                (Value, Cow::Borrowed("\\")),
+                (Eof, Cow::Borrowed("")),
+            ]
+        );
+
+        let config = crate::parse_config("channel\n    \\#nccl\n").unwrap();
+        assert_eq!(config["channel"].value(), Some("#nccl"));
+        assert_eq!(config["channel"]["#nccl"].key().matches('#').count(), 1);
+        assert_eq!(config.to_string(), "channel\n    \\#nccl\n");
+        assert_eq!(crate::parse_config(&config.to_string()).unwrap(), config);
+
+        let config = crate::parse_config("key\n    my \\# value\n").unwrap();
+        assert_eq!(config["key"].child().unwrap().key(), "my # value");
+        assert_eq!(config["key"].value(), Some("my # value"));
+        assert!(config["key"].has_value("my # value"));
+        assert_eq!(config.to_string(), "key\n    my # value\n");
+        assert_eq!(crate::parse_config(&config.to_string()).unwrap(), config);
+
+        // a backslash before the comment character can't be written bare
+        let mut config = crate::Config::new(crate::parser::TOP_LEVEL_KEY, None);
+        config.insert_child("a \\# b");
+        assert_eq!(config.to_string(), "\"a \\\\# b\"\n");
+        let printed = config.to_string();
+        let reparsed = crate::parse_config(&printed).unwrap();
+        assert_eq!(reparsed.child().unwrap().parse_quoted().unwrap(), "a \\# b");
+
+        let options = ParseOptions {
+            comment_char: ';',
+            ..ParseOptions::default()
+        };
+        let source = "key\n    \\;one\n    #two\n    a \\; b\n    c \\# d\n";
+        let config = crate::parse_config_with_options(source, options).unwrap();
+        assert_eq!(
+            config["key"].values().collect::<Vec<_>>(),
+            vec![";one", "#two", "a ; b", "c \\# d"]
+        );
+        assert_eq!(
+            config.to_string(),
+            "key\n    \\;one\n    #two\n    a ; b\n    c \\# d\n"
+        );
+        assert_eq!(
+            crate::parse_config_with_options(&config.to_string(), options).unwrap(),
+            config
+        );
+    }
+
+    #[test]
+    fn offsets() {
+        let source = "key\n    'value'\n";
+        let tokens = Scanner::new(source).scan_all().unwrap();
+        assert_eq!((tokens[1].span.offset, tokens[1].span.len), (4, 4));
+        assert_eq!((tokens[2].span.offset, tokens[2].span.len), (9, 5));
+        assert_eq!(
+            &source[tokens[2].span.offset..tokens[2].span.offset + tokens[2].span.len],
+            "value"
+        );
+
+        let config = crate::parse_config(source).unwrap();
+        assert_eq!(config["key"].span().offset, 0);
+        assert_eq!(config["key"].span().len, 3);
+    }
+
+    #[test]
+    fn unknown_escape_column() {
+        assert_eq!(
+            Scanner::new("\"ab\\q\"\n").scan_all(),
+            Err(NcclError::ScanUnknownEscape {
+                line: 1,
+                column: 4,
+                escape: 'q',
+            })
+        );
+
+        // é and 🇳🇴 are more than one byte each
+        assert_eq!(
+            Scanner::new("key\n  \"é🇳🇴\\q\"\n").scan_all(),
+            Err(NcclError::ScanUnknownEscape {
+                line: 2,
+                column: 7,
+                escape: 'q',
+            })
+        );
+    }
+
+    #[test]
+    fn public_tokens() {
+        let lexemes = tokens("a\n    \"b\"\n")
+            .map(|token| token.map(|token| token.lexeme))
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+        assert_eq!(lexemes, vec!["a", "    ", "b", ""]);
+
+        let mut iter = tokens("a\n    \"b\n");
+        assert_eq!(iter.next().unwrap().unwrap().lexeme, "a");
+        assert_eq!(
+            iter.next(),
+            Some(Err(NcclError::UnterminatedString { start: 2 }))
+        );
+        assert_eq!(iter.next(), None);
+    }
+
+    #[test]
+    fn leading_space_escape() {
+        let source = "text\n    \\ hello\n    \\\\ not escaped\n";
+        let config = crate::parse_config(source).unwrap();
+        assert_eq!(
+            config["text"].values().collect::<Vec<_>>(),
+            vec![" hello", "\\\\ not escaped"]
+        );
+        assert!(config["text"].value().unwrap().starts_with(' '));
+
+        let printed = config.to_string();
+        assert_eq!(crate::parse_config(&printed).unwrap(), config);
+    }
+
+    #[test]
+    fn quote_kind_hash_ord() {
+        let set = [QuoteKind::Double, QuoteKind::Single, QuoteKind::Double]
+            .into_iter()
+            .collect::<std::collections::HashSet<_>>();
+        assert_eq!(set.len(), 2);
+        assert!(set.contains(&QuoteKind::Single));
+        assert!(set.contains(&QuoteKind::Double));
+        assert!(QuoteKind::Single < QuoteKind::Double);
+    }
+
+    #[test]
+    fn c_comments() {
+        let source = r#"// line comment
+server
+    /* a block
+comment, at any
+            indentation */
+    port
+        80
+        /* one line */
+        "443" // after a quote
+    // another
+    root
+        "/var/www" /* also after a quote */
+"#;
+        let options = ParseOptions {
+            c_comments: true,
+            ..ParseOptions::default()
+        };
+
+        let config = crate::parse_config_with_options(source, options).unwrap();
+        assert_eq!(config.values().collect::<Vec<_>>(), vec!["server"]);
+        assert_eq!(
+            config["server"].values().collect::<Vec<_>>(),
+            vec!["port", "root"]
+        );
+        assert_eq!(
+            config["server"]["port"].values().collect::<Vec<_>>(),
+            vec!["80", "443"]
+        );
+        assert_eq!(config["server"]["root"].value(), Some("/var/www"));
+        assert_eq!(config["server"].span().line, 2);
+        assert_eq!(config["server"]["port"].span().line, 6);
+
+        // off by default
+        let config = crate::parse_config("// not a comment\n").unwrap();
+        assert!(config.has_value("// not a comment"));
+
+        assert_eq!(
+            crate::parse_config_with_options("a\n/* never\n  ends\n", options),
+            Err(NcclError::UnterminatedComment { start: 2 })
+        );
+        assert_eq!(
+            crate::parse_config_with_options("a\n/* hi */ b\n", options),
+            Err(NcclError::TrailingCharacters { line: 2 })
+        );
+    }
+
+    #[test]
+    fn default_span() {
+        let span = Span::default();
+        assert_eq!((span.line, span.column), (0, 0));
+        assert_eq!((span.offset, span.len), (0, 0));
+
+        let config = crate::parse_config("key\n").unwrap();
+        assert_ne!(config["key"].span(), span);
+        assert_eq!(config.span(), span);
+    }
+
+    #[test]
+    fn mixed_indentation() {
+        let err = Scanner::new("a\n\t\tb\n\t c\n").scan_all().unwrap_err();
+        assert_eq!(
+            err,
+            NcclError::MixedIndentation {
+                span: Span {
+                    line: 3,
+                    column: 2,
+                    offset: 6,
+                    len: 2,
+                },
+            }
+        );
+
+        let err = Scanner::new("a\n  \tb\n").scan_all().unwrap_err();
+        assert!(matches!(err, NcclError::MixedIndentation { span } if span.len == 3));
+
+        // blank lines and comments may still be indented with anything
+        assert!(Scanner::new("a\n\t \n \t# c\n\tb\n").scan_all().is_ok());
+    }
+
+    #[test]
+    fn indent_char() {
+        let source =
+            "server\n\x0B\x0Bport\n\x0B\x0B\x0B\x0B80\n  \x0B \n\x0B\x0Broot\n    /var/www\n";
+        let options = ParseOptions {
+            indent_char: Some('\x0B'),
+            ..ParseOptions::default()
+        };
+
+        let config = crate::parse_config_with_options(source, options).unwrap();
+        assert_eq!(config["server"]["port"].value(), Some("80"));
+        assert_eq!(config["server"]["root"].value(), Some("/var/www"));
+
+        // by default, vertical tabs are part of the value
+        let config = crate::parse_config("server\n\x0B\x0Bport\n").unwrap();
+        assert!(config.has_value("server"));
+        assert!(config.has_value("\x0B\x0Bport"));
+
+        for indent_char in ['\t', '\n', '\r', '\u{3000}', '"', '#', '\\', 'a', '\0'] {
+            let options = ParseOptions {
+                indent_char: Some(indent_char),
+                ..ParseOptions::default()
+            };
+            assert_eq!(
+                crate::parse_config_with_options(source, options),
+                Err(NcclError::InvalidIndentChar { indent_char })
+            );
+        }
+    }
 }
@@ -0,0 +1,73 @@
+//! Serde serialization for [`Config`]
+
+use crate::config::Config;
+
+use alloc::borrow::Cow;
+use serde::ser::{Error, Serialize, SerializeMap, SerializeSeq, Serializer};
+
+/// A leaf is serialized as a string of its key, a node whose children are all
+/// leaves as a sequence of their keys, and any other node as a map from the
+/// key of each child to the child. Quoted keys have their escape sequences
+/// processed by [`Config::parse_quoted`].
+///
+/// ```
+/// let source = "server\n    port\n        80\n        443\n    root\n        /var/www\n";
+/// let config = nccl::parse_config(&source).unwrap();
+/// assert_eq!(
+///     serde_json::to_string(&config).unwrap(),
+///     r#"{"server":{"port":["80","443"],"root":["/var/www"]}}"#
+/// );
+/// ```
+impl Serialize for Config<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        if self.is_empty() {
+            serializer.serialize_str(&unescaped_key(self)?)
+        } else if self.children().all(Config::is_empty) {
+            let mut seq = serializer.serialize_seq(Some(self.len()))?;
+            for child in self.children() {
+                seq.serialize_element(child)?;
+            }
+            seq.end()
+        } else {
+            let mut map = serializer.serialize_map(Some(self.len()))?;
+            for child in self.children() {
+                map.serialize_entry(&unescaped_key(child)?, child)?;
+            }
+            map.end()
+        }
+    }
+}
+
+fn unescaped_key<'a, E: Error>(node: &'a Config) -> Result<Cow<'a, str>, E> {
+    if node.quoted() {
+        node.parse_quoted().map(Cow::Owned).map_err(E::custom)
+    } else {
+        Ok(Cow::Borrowed(node.key()))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    #[test]
+    fn serialize() {
+        let source = std::fs::read_to_string("examples/config.nccl").unwrap();
+        let config = crate::parse_config(&source).unwrap();
+        let json = serde_json::to_value(&config).unwrap();
+        assert_eq!(json["server"]["port"], serde_json::json!(["80", "443"]));
+        assert_eq!(
+            json["server"]["domain"],
+            serde_json::json!(["example.com", "www.example.com"])
+        );
+        assert_eq!(json["server"]["root"], serde_json::json!(["/var/www/html"]));
+
+        let config = crate::parse_config("a\n    'b\\tc'\n").unwrap();
+        assert_eq!(
+            serde_json::to_value(&config["a"]).unwrap(),
+            serde_json::json!(["b\tc"])
+        );
+        assert_eq!(
+            serde_json::to_value(&config["a"]["b\\tc"]).unwrap(),
+            serde_json::json!("b\tc")
+        );
+    }
+}